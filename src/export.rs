@@ -0,0 +1,437 @@
+// See licence at the end.
+
+// By Jefferson T.
+// https://jeffersontorres.com.br
+
+//! Text versions of a presentation, built over the parsed `Slide`s
+//! rather than over the `HTML`. Draft slides are left out and the
+//! numbering follows the one seen during the presentation.
+
+use crate::{Deck, Element, ElementNature, Metadata, Slide};
+
+/// Non-draft slides paired with their presentation number.
+fn numbered(slides: &[Slide]) -> impl Iterator<Item = (usize, &Slide)> {
+    slides
+        .iter()
+        .filter_map(|slide| slide.number.map(|number| (number, slide)))
+}
+
+/// The elements of a slide, or none if it failed to build.
+fn elements(slide: &Slide) -> &[Element] {
+    match &slide.content {
+        Ok(elements) => elements,
+        Err(_) => &[],
+    }
+}
+
+/// Splits a table row written as `a | b | c` into its cells.
+fn cells(row: &str) -> Vec<&str> {
+    row.split('|').map(|cell| cell.trim()).collect()
+}
+
+/// Title, author and date lines of the `Metadata`, when present.
+fn meta_lines(meta: &Metadata) -> Vec<&str> {
+    [&meta.title, &meta.author, &meta.date]
+        .into_iter()
+        .filter_map(|field| field.as_deref())
+        .collect()
+}
+
+/// A markdown handout of the presentation.
+pub fn markdown(deck: &Deck) -> String {
+    let mut out = String::new();
+    if let Some(title) = &deck.meta.title {
+        out += &format!("# {}\n\n", title);
+    }
+    let byline: Vec<&str> = [&deck.meta.author, &deck.meta.date]
+        .into_iter()
+        .filter_map(|field| field.as_deref())
+        .collect();
+    if !byline.is_empty() {
+        out += &format!("*{}*\n\n", byline.join(", "));
+    }
+    for (number, slide) in numbered(&deck.slides) {
+        out += &format!("## Slide {}\n\n", number);
+        for element in elements(slide).iter().chain(&slide.background) {
+            let source = &element.source;
+            match element.nature {
+                ElementNature::Heading => out += &format!("### {}\n", source[0]),
+                ElementNature::Subheading => out += &format!("#### {}\n", source[0]),
+                ElementNature::Text => out += &format!("{}\n", source.join("  \n")),
+                ElementNature::List => {
+                    for item in source {
+                        out += &format!("- {}\n", item);
+                    }
+                }
+                ElementNature::OrdList => {
+                    let start = element.start.unwrap_or(1);
+                    for (i, item) in source.iter().enumerate() {
+                        out += &format!("{}. {}\n", start + i, item);
+                    }
+                }
+                ElementNature::Table => {
+                    let header = cells(&source[0]);
+                    out += &format!("| {} |\n", header.join(" | "));
+                    out += &format!("|{}\n", " --- |".repeat(header.len()));
+                    for row in &source[1..] {
+                        out += &format!("| {} |\n", cells(row).join(" | "));
+                    }
+                }
+                ElementNature::Image => {
+                    out += &format!("![{}]({})\n", source[1..].join(" "), source[0]);
+                }
+                ElementNature::Video => out += &format!("[Video]({})\n", source[0]),
+                ElementNature::Audio => out += &format!("[Audio]({})\n", source[0]),
+                ElementNature::Mermaid => {
+                    out += &format!("```mermaid\n{}\n```\n", source.join("\n"))
+                }
+            }
+            out += "\n";
+        }
+        if !slide.notes.is_empty() {
+            out += &format!("> **Notes:** {}\n\n", slide.notes.join("  \n> "));
+        }
+    }
+    out
+}
+
+/// A plain text outline of the presentation, one indented block per
+/// slide, suited for screen readers and wikis.
+pub fn outline(deck: &Deck) -> String {
+    const INDENT: &str = "    ";
+    let mut out = String::new();
+    let meta = meta_lines(&deck.meta);
+    if !meta.is_empty() {
+        out += &format!("{}\n\n", meta.join("\n"));
+    }
+    for (number, slide) in numbered(&deck.slides) {
+        out += &format!("Slide {}\n", number);
+        for element in elements(slide).iter().chain(&slide.background) {
+            let source = &element.source;
+            match element.nature {
+                ElementNature::Heading | ElementNature::Subheading | ElementNature::Text => {
+                    for line in source {
+                        out += &format!("{}{}\n", INDENT, line);
+                    }
+                }
+                ElementNature::List => {
+                    for item in source {
+                        out += &format!("{}- {}\n", INDENT, item);
+                    }
+                }
+                ElementNature::OrdList => {
+                    let start = element.start.unwrap_or(1);
+                    for (i, item) in source.iter().enumerate() {
+                        out += &format!("{}{}) {}\n", INDENT, start + i, item);
+                    }
+                }
+                ElementNature::Table => {
+                    for row in source {
+                        out += &format!("{}{}\n", INDENT, cells(row).join(" | "));
+                    }
+                }
+                ElementNature::Image => {
+                    out += &format!("{}[Image: {}]", INDENT, source[0]);
+                    if source.len() > 1 {
+                        out += &format!(" {}", source[1..].join(" "));
+                    }
+                    out += "\n";
+                }
+                ElementNature::Video => out += &format!("{}[Video: {}]\n", INDENT, source[0]),
+                ElementNature::Audio => out += &format!("{}[Audio: {}]\n", INDENT, source[0]),
+                ElementNature::Mermaid => out += &format!("{}[Diagram]\n", INDENT),
+            }
+        }
+        if !slide.notes.is_empty() {
+            out += &format!("{}Notes: {}\n", INDENT, slide.notes.join(" "));
+        }
+        out += "\n";
+    }
+    out
+}
+
+/// Version of the schema written by `json()`. Bumped on any change that
+/// may break a consumer; adding new keys is not one of them.
+pub const JSON_SCHEMA_VERSION: u32 = 2;
+
+/// A `JSON` string literal, escaped as required by RFC 8259.
+fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out + "\""
+}
+
+/// A `JSON` array of strings.
+fn quote_all<T: AsRef<str>>(lines: &[T]) -> String {
+    let quoted: Vec<String> = lines.iter().map(|line| quote(line.as_ref())).collect();
+    format!("[{}]", quoted.join(","))
+}
+
+/// `null` or a `JSON` string.
+fn quote_option(text: &Option<String>) -> String {
+    match text {
+        Some(text) => quote(text),
+        None => "null".to_string(),
+    }
+}
+
+/// A `JSON` object from its already serialized values.
+fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}:{}", quote(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+/// One element as a `JSON` object.
+fn json_element(element: &Element) -> String {
+    let source = &element.source;
+    let (text, media, cells) = match element.nature {
+        ElementNature::Image | ElementNature::Video | ElementNature::Audio => {
+            (&source[1..], quote(&source[0]), "null".to_string())
+        }
+        ElementNature::Table => {
+            let rows: Vec<String> = source.iter().map(|row| quote_all(&cells(row))).collect();
+            (
+                &source[..],
+                "null".to_string(),
+                format!("[{}]", rows.join(",")),
+            )
+        }
+        _ => (&source[..], "null".to_string(), "null".to_string()),
+    };
+    object(&[
+        ("nature", quote(element.nature.tag())),
+        ("text", quote_all(text)),
+        ("media", media),
+        ("cells", cells),
+        (
+            "start",
+            element
+                .start
+                .map_or("null".to_string(), |start| start.to_string()),
+        ),
+    ])
+}
+
+/// The parsed presentation as `JSON`, so tools can consume a deck
+/// without reimplementing the parsing rules. The schema, at
+/// `JSON_SCHEMA_VERSION` 2, is:
+///
+/// ```text
+/// {
+///   "version": 2,
+///   "meta": {
+///     "title": string | null,
+///     "author": string | null,
+///     "date": string | null,
+///     "lang": string | null,
+///     "subtitle": string | null,
+///     "event": string | null
+///   },
+///   "canvas": { "width": integer, "height": integer },  in pixels
+///   "fonts": [{ "path": string, "family": string }],
+///   "slides": [{
+///     "number": integer | null,      null on draft slides
+///     "kind": string,                "content", "title", "section" or "agenda"
+///     "id": string | null,           given by `.id`
+///     "section": string | null,      the last `.section` up to the slide
+///     "draft": boolean,
+///     "elements": [{
+///       "nature": string,            the tag: "heading", "image", ...
+///       "text": [string],            lines after the tag, minus the media path
+///       "media": string | null,      path of an "image", "video" or "audio"
+///       "cells": [[string]] | null,  "table" only, header row first
+///       "start": integer | null      first number of an "ordlist" going
+///                                    on from a split one
+///     }],
+///     "notes": [string],
+///     "background": element | null,  an "audio" played behind the slide
+///     "layout": string | null,       asked by `.layout`, like "sidebar"
+///     "footer": string | null,
+///     "logo": string | null          path as written in the input
+///   }]
+/// }
+/// ```
+///
+/// Version 1 had a single `"footer"` and `"logo"` at the top level.
+pub fn json(deck: &Deck) -> String {
+    let slides: Vec<String> = deck
+        .slides
+        .iter()
+        .map(|slide| {
+            let slide_number = match slide.number {
+                Some(number) => number.to_string(),
+                None => "null".to_string(),
+            };
+            let elements: Vec<String> = elements(slide).iter().map(json_element).collect();
+            object(&[
+                ("number", slide_number),
+                ("kind", quote(slide.kind.name())),
+                ("id", quote_option(&slide.id)),
+                ("section", quote_option(&slide.section)),
+                ("draft", slide.draft.to_string()),
+                ("elements", format!("[{}]", elements.join(","))),
+                ("notes", quote_all(&slide.notes)),
+                (
+                    "background",
+                    match &slide.background {
+                        Some(audio) => json_element(audio),
+                        None => "null".to_string(),
+                    },
+                ),
+                (
+                    "layout",
+                    quote_option(&slide.layout.map(|layout| layout.name().to_string())),
+                ),
+                ("footer", quote_option(&slide.footer)),
+                (
+                    "logo",
+                    quote_option(&slide.logo.as_ref().map(|logo| logo.path.clone())),
+                ),
+            ])
+        })
+        .collect();
+    object(&[
+        ("version", JSON_SCHEMA_VERSION.to_string()),
+        (
+            "meta",
+            object(&[
+                ("title", quote_option(&deck.meta.title)),
+                ("author", quote_option(&deck.meta.author)),
+                ("date", quote_option(&deck.meta.date)),
+                ("lang", quote_option(&deck.meta.lang)),
+                ("subtitle", quote_option(&deck.meta.subtitle)),
+                ("event", quote_option(&deck.meta.event)),
+            ]),
+        ),
+        (
+            "canvas",
+            object(&[
+                ("width", deck.canvas.width.to_string()),
+                ("height", deck.canvas.height.to_string()),
+            ]),
+        ),
+        (
+            "fonts",
+            format!(
+                "[{}]",
+                deck.fonts
+                    .iter()
+                    .map(|font| object(&[
+                        ("path", quote(&font.path)),
+                        ("family", quote(&font.family)),
+                    ]))
+                    .collect::<Vec<String>>()
+                    .join(",")
+            ),
+        ),
+        ("slides", format!("[{}]", slides.join(","))),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Assets};
+
+    fn deck(lines: &[&str]) -> Deck {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        parse(lines, &Assets::Embedded).unwrap()
+    }
+
+    #[test]
+    fn quote_escapes() {
+        assert_eq!(quote("plain"), r#""plain""#);
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(r"a\b"), r#""a\\b""#);
+        assert_eq!(quote("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(quote("\u{1}\u{1f}"), r#""\u0001\u001f""#);
+        assert_eq!(quote("ação ✓"), "\"ação ✓\"");
+        assert_eq!(quote_all(&["a", "b"]), r#"["a","b"]"#);
+        assert_eq!(quote_all::<&str>(&[]), "[]");
+    }
+
+    #[test]
+    fn json_top_level() {
+        let json = json(&deck(&[".title Talk", "---", ".text", "Hi"]));
+        assert!(json.starts_with(&format!(
+            r#"{{"version":{},"meta":{{"title":"Talk","#,
+            JSON_SCHEMA_VERSION
+        )));
+        let keys = [
+            r#"{"version":"#,
+            r#","meta":{"#,
+            r#","canvas":{"width":1280,"height":720},"#,
+            r#""fonts":[],"#,
+            r#""slides":[{"#,
+        ];
+        let mut at = 0;
+        for key in keys {
+            let found = json[at..].find(key).unwrap_or_else(|| panic!("{}", key));
+            at += found + key.len();
+        }
+        assert!(json.ends_with("}]}"));
+    }
+
+    #[test]
+    fn json_unchanged() {
+        // Bump `JSON_SCHEMA_VERSION` along with this output if a
+        // consumer could break.
+        let deck = deck(&[
+            r#".title Say "hi""#,
+            ".author Me",
+            "---",
+            ".id intro",
+            ".heading",
+            "Hello",
+            ".text",
+            r"a \\ b",
+            ".notes",
+            "breathe",
+            "---",
+            ".ordlist",
+            "one",
+        ]);
+        assert_eq!(
+            json(&deck),
+            concat!(
+                r#"{"version":2,"meta":{"title":"Say \"hi\"","author":"Me","date":null,"lang":null,"subtitle":null,"event":null},"#,
+                r#""canvas":{"width":1280,"height":720},"fonts":[],"slides":["#,
+                r#"{"number":1,"kind":"content","id":"intro","section":null,"draft":false,"elements":["#,
+                r#"{"nature":"heading","text":["Hello"],"media":null,"cells":null,"start":null},"#,
+                r#"{"nature":"text","text":["a \\\\ b"],"media":null,"cells":null,"start":null}],"#,
+                r#""notes":["breathe"],"background":null,"layout":null,"footer":null,"logo":null},"#,
+                r#"{"number":2,"kind":"content","id":null,"section":null,"draft":false,"elements":["#,
+                r#"{"nature":"ordlist","text":["one"],"media":null,"cells":null,"start":null}],"#,
+                r#""notes":[],"background":null,"layout":null,"footer":null,"logo":null}]}"#,
+            )
+        );
+    }
+}
+
+//    This file is part of StultusVisio.
+//
+//    StultusVisio is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    any later version.
+//
+//    StultusVisio is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with StultusVisio.  If not, see <https://www.gnu.org/licenses/>.
@@ -0,0 +1,486 @@
+// See licence at the end.
+
+// By Jefferson T.
+// https://jeffersontorres.com.br
+
+//! How the elements of a slide are arranged. Each layout is a `CSS`
+//! grid template with named areas, `a`, `b`, `c` and `d`, given to the
+//! elements in order. Adding a layout means adding a `Template` to
+//! `TEMPLATES` and a case to `choose()`.
+
+use crate::{Canvas, Element, ElementNature};
+
+/// Names of the areas, in the order the elements fill them.
+pub const AREAS: [&str; 4] = ["a", "b", "c", "d"];
+
+/// A layout asked by `.layout`, instead of the one chosen from the
+/// elements. Headings always stay on top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Two equal columns, filled from the left.
+    TwoColumns,
+    /// The first element large on the left, the others stacked on its
+    /// right.
+    Sidebar,
+    /// The first element large on top, the others side by side below.
+    BigTop,
+    /// Two by two.
+    Quadrants,
+    /// The first element over the whole slide, edge to edge, under the
+    /// others.
+    FullBleed,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 5] = [
+        Layout::TwoColumns,
+        Layout::Sidebar,
+        Layout::BigTop,
+        Layout::Quadrants,
+        Layout::FullBleed,
+    ];
+
+    /// Name given to `.layout`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TwoColumns => "two-columns",
+            Self::Sidebar => "sidebar",
+            Self::BigTop => "big-top",
+            Self::Quadrants => "quadrants",
+            Self::FullBleed => "full-bleed",
+        }
+    }
+}
+
+/// A grid the elements are placed on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Template {
+    /// Unique, gives the `layout-{name}` class.
+    pub name: &'static str,
+    /// The `grid-template-areas`, one string per row.
+    pub areas: &'static [&'static str],
+    pub columns: &'static str,
+    pub rows: &'static str,
+    /// The first element fills the grid and the others go over it.
+    pub overlay: bool,
+}
+
+const fn template(
+    name: &'static str,
+    areas: &'static [&'static str],
+    columns: &'static str,
+    rows: &'static str,
+) -> Template {
+    Template {
+        name,
+        areas,
+        columns,
+        rows,
+        overlay: false,
+    }
+}
+
+pub const SINGLE: Template = template("single", &["a"], "1fr", "1fr");
+pub const STACKED: Template = template("stacked", &["a", "b"], "1fr", "auto 1fr");
+pub const SIDE_BY_SIDE: Template = template("side-by-side", &["a b"], "1fr 1fr", "1fr");
+pub const COLUMN: Template = template("column", &["a", "b", "c"], "1fr", "1fr 1fr 1fr");
+pub const PYRAMID: Template = template("pyramid", &["a a", "b c"], "1fr 1fr", "1fr 1fr");
+pub const SQUARE: Template = template("square", &["a b", "c d"], "1fr 1fr", "1fr 1fr");
+pub const TWO_COLUMNS: Template = template("two-columns", &["a b"], "1fr 1fr", "1fr");
+pub const TWO_COLUMNS_3: Template =
+    template("two-columns-3", &["a c", "b c"], "1fr 1fr", "1fr 1fr");
+pub const TWO_COLUMNS_4: Template =
+    template("two-columns-4", &["a c", "b d"], "1fr 1fr", "1fr 1fr");
+pub const SIDEBAR: Template = template("sidebar", &["a b"], "2fr 1fr", "1fr");
+pub const SIDEBAR_3: Template = template("sidebar-3", &["a b", "a c"], "2fr 1fr", "1fr 1fr");
+pub const SIDEBAR_4: Template = template(
+    "sidebar-4",
+    &["a b", "a c", "a d"],
+    "2fr 1fr",
+    "1fr 1fr 1fr",
+);
+pub const BIG_TOP: Template = template("big-top", &["a"], "1fr", "1fr");
+pub const BIG_TOP_2: Template = template("big-top-2", &["a", "b"], "1fr", "2fr 1fr");
+pub const BIG_TOP_3: Template = template("big-top-3", &["a a", "b c"], "1fr 1fr", "2fr 1fr");
+pub const BIG_TOP_4: Template =
+    template("big-top-4", &["a a a", "b c d"], "1fr 1fr 1fr", "2fr 1fr");
+pub const QUADRANTS: Template = template("quadrants", &["a b", "c d"], "1fr 1fr", "1fr 1fr");
+pub const FULL_BLEED: Template = Template {
+    overlay: true,
+    ..template("full-bleed", &["a"], "1fr", "1fr")
+};
+
+/// Every template, for their `CSS`.
+pub const TEMPLATES: [&Template; 18] = [
+    &SINGLE,
+    &STACKED,
+    &SIDE_BY_SIDE,
+    &COLUMN,
+    &PYRAMID,
+    &SQUARE,
+    &TWO_COLUMNS,
+    &TWO_COLUMNS_3,
+    &TWO_COLUMNS_4,
+    &SIDEBAR,
+    &SIDEBAR_3,
+    &SIDEBAR_4,
+    &BIG_TOP,
+    &BIG_TOP_2,
+    &BIG_TOP_3,
+    &BIG_TOP_4,
+    &QUADRANTS,
+    &FULL_BLEED,
+];
+
+/// The template for the natures of the elements to place, in order.
+/// With a `.layout`, the headings are not placed and so not given.
+pub fn choose(layout: Option<Layout>, natures: &[ElementNature]) -> Option<&'static Template> {
+    let heading = |nature: &ElementNature| nature.is_heading_or_subheading();
+    if natures.is_empty() && layout != Some(Layout::FullBleed) {
+        return None;
+    }
+    let template = match (layout, natures) {
+        (None, [_]) => &SINGLE,
+        (None, [first, _]) if heading(first) => &STACKED,
+        (None, [_, _]) => &SIDE_BY_SIDE,
+        (None, [_, second, third]) if heading(second) || heading(third) => &COLUMN,
+        (None, [_, _, _]) => &PYRAMID,
+        (None, [_, _, _, _]) => &SQUARE,
+        (None, _) => return None,
+        (Some(Layout::TwoColumns), [_] | [_, _]) => &TWO_COLUMNS,
+        (Some(Layout::TwoColumns), [_, _, _]) => &TWO_COLUMNS_3,
+        (Some(Layout::TwoColumns), _) => &TWO_COLUMNS_4,
+        (Some(Layout::Sidebar), [_] | [_, _]) => &SIDEBAR,
+        (Some(Layout::Sidebar), [_, _, _]) => &SIDEBAR_3,
+        (Some(Layout::Sidebar), _) => &SIDEBAR_4,
+        (Some(Layout::BigTop), [_]) => &BIG_TOP,
+        (Some(Layout::BigTop), [_, _]) => &BIG_TOP_2,
+        (Some(Layout::BigTop), [_, _, _]) => &BIG_TOP_3,
+        (Some(Layout::BigTop), _) => &BIG_TOP_4,
+        (Some(Layout::Quadrants), _) => &QUADRANTS,
+        (Some(Layout::FullBleed), _) => &FULL_BLEED,
+    };
+    Some(template)
+}
+
+/// The `CSS` of every template.
+pub fn css() -> String {
+    let mut css = String::new();
+    for template in TEMPLATES {
+        let areas: Vec<String> = template
+            .areas
+            .iter()
+            .map(|row| format!("\"{}\"", row))
+            .collect();
+        css += &format!(
+            ".layout-{} {{ grid-template-areas: {}; grid-template-columns: {}; grid-template-rows: {}; }}",
+            template.name,
+            areas.join(" "),
+            template.columns,
+            template.rows
+        );
+    }
+    for area in AREAS {
+        css += &format!(".layout > .area-{} {{ grid-area: {}; }}", area, area);
+    }
+    css
+}
+
+/// The elements of a slide on their grid. With a `.layout`, the
+/// headings stay above it, or over the first element of a
+/// `Template::overlay`.
+pub fn render(layout: Option<Layout>, elements: &[Element]) -> String {
+    let (headings, placed): (Vec<&Element>, Vec<&Element>) = match layout {
+        Some(_) => elements
+            .iter()
+            .partition(|element| element.nature.is_heading_or_subheading()),
+        None => (vec![], elements.iter().collect()),
+    };
+    let contents = |elements: &[&Element]| {
+        elements
+            .iter()
+            .map(|element| element.content.as_str())
+            .collect::<String>()
+    };
+    let natures: Vec<ElementNature> = placed
+        .iter()
+        .map(|element| element.nature.clone())
+        .collect();
+    let template = match choose(layout, &natures) {
+        Some(template) => template,
+        // Past the four areas, when `max-elements` allows it.
+        None => return contents(&headings) + &contents(&placed),
+    };
+    let area = |name: Option<&str>, content: String| match name {
+        Some(name) => format!("<div class=\"area area-{}\">{}</div>", name, content),
+        // Placed by the grid on rows of its own.
+        None => format!("<div class=\"area\">{}</div>", content),
+    };
+    let (above, grid) = match template.overlay {
+        true => {
+            let (first, rest) = placed.split_at(placed.len().min(1));
+            (
+                String::new(),
+                area(Some(AREAS[0]), contents(first))
+                    + &format!(
+                        "<div class=\"area over\">{}{}</div>",
+                        contents(&headings),
+                        contents(rest)
+                    ),
+            )
+        }
+        false => (
+            contents(&headings),
+            placed
+                .iter()
+                .enumerate()
+                .map(|(i, element)| area(AREAS.get(i).copied(), element.content.clone()))
+                .collect(),
+        ),
+    };
+    format!(
+        "{}<div class=\"layout layout-{}\">{}</div>",
+        above, template.name, grid
+    )
+}
+
+/// Size of a character of text at full size, in square pixels, line
+/// spacing and margins included.
+const CHAR_AREA: f64 = 220.0;
+
+/// Part of the slide left to the grid by the padding and headings.
+const USABLE: f64 = 0.7;
+
+/// Share of the grid taken by each of `AREAS`.
+fn shares(template: &Template) -> Vec<f64> {
+    let cells: Vec<&str> = template
+        .areas
+        .iter()
+        .flat_map(|row| row.split_whitespace())
+        .collect();
+    AREAS
+        .iter()
+        .map(|area| cells.iter().filter(|cell| *cell == area).count() as f64 / cells.len() as f64)
+        .collect()
+}
+
+/// A guess, as the compiler can't measure the text, of the scale the
+/// runtime brings the texts and tables of a slide down to for them to
+/// fit in their areas. 1 when nothing has to shrink.
+pub fn estimated_scale(layout: Option<Layout>, elements: &[Element], canvas: Canvas) -> f64 {
+    let placed: Vec<&Element> = match layout {
+        Some(_) => elements
+            .iter()
+            .filter(|element| !element.nature.is_heading_or_subheading())
+            .collect(),
+        None => elements.iter().collect(),
+    };
+    let natures: Vec<ElementNature> = placed
+        .iter()
+        .map(|element| element.nature.clone())
+        .collect();
+    let shares = match choose(layout, &natures) {
+        Some(template) if !template.overlay => shares(template),
+        Some(_) => vec![1.0; placed.len()],
+        None => vec![1.0 / placed.len().max(1) as f64; placed.len()],
+    };
+    let slide = canvas.width as f64 * canvas.height as f64 * USABLE;
+    placed
+        .iter()
+        .zip(shares.into_iter().chain(std::iter::repeat(0.0)))
+        .filter(|(element, _)| matches!(element.nature, ElementNature::Text | ElementNature::Table))
+        .map(|(element, share)| {
+            let chars: usize = element.source.iter().map(|line| line.chars().count()).sum();
+            // The lines of the source never end up shorter than this.
+            let chars = chars.max(element.source.len() * 20);
+            (slide * share / (chars as f64 * CHAR_AREA)).sqrt()
+        })
+        .fold(1.0, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::element;
+    use ElementNature::*;
+
+    #[test]
+    fn automatic_by_natures() {
+        assert_eq!(choose(None, &[]), None);
+        assert_eq!(choose(None, &[Image]), Some(&SINGLE));
+        assert_eq!(choose(None, &[Heading, Text]), Some(&STACKED));
+        assert_eq!(choose(None, &[Subheading, Image]), Some(&STACKED));
+        assert_eq!(choose(None, &[Text, Image]), Some(&SIDE_BY_SIDE));
+        assert_eq!(choose(None, &[Heading, Subheading, Text]), Some(&COLUMN));
+        assert_eq!(choose(None, &[Heading, Text, Subheading]), Some(&COLUMN));
+        assert_eq!(choose(None, &[Heading, Text, Image]), Some(&PYRAMID));
+        assert_eq!(choose(None, &[Text, List, Image]), Some(&PYRAMID));
+        assert_eq!(choose(None, &[Heading, Text, List, Image]), Some(&SQUARE));
+        assert_eq!(choose(None, &[Text, Text, Text, Text, Text]), None);
+    }
+
+    #[test]
+    fn named_by_count() {
+        let texts = |n: usize| vec![Text; n];
+        let cases = [
+            (
+                Layout::TwoColumns,
+                [&TWO_COLUMNS, &TWO_COLUMNS, &TWO_COLUMNS_3, &TWO_COLUMNS_4],
+            ),
+            (
+                Layout::Sidebar,
+                [&SIDEBAR, &SIDEBAR, &SIDEBAR_3, &SIDEBAR_4],
+            ),
+            (
+                Layout::BigTop,
+                [&BIG_TOP, &BIG_TOP_2, &BIG_TOP_3, &BIG_TOP_4],
+            ),
+            (Layout::Quadrants, [&QUADRANTS; 4]),
+            (Layout::FullBleed, [&FULL_BLEED; 4]),
+        ];
+        for (layout, templates) in cases {
+            for (n, template) in templates.into_iter().enumerate() {
+                assert_eq!(
+                    choose(Some(layout), &texts(n + 1)),
+                    Some(template),
+                    "{:?} {}",
+                    layout,
+                    n + 1
+                );
+            }
+        }
+        assert_eq!(choose(Some(Layout::Sidebar), &[]), None);
+        assert_eq!(choose(Some(Layout::FullBleed), &[]), Some(&FULL_BLEED));
+    }
+
+    #[test]
+    fn templates_are_well_formed() {
+        for template in TEMPLATES {
+            let rows: Vec<Vec<&str>> = template
+                .areas
+                .iter()
+                .map(|row| row.split_whitespace().collect())
+                .collect();
+            let columns = template.columns.split_whitespace().count();
+            assert!(
+                rows.iter().all(|row| row.len() == columns),
+                "{}",
+                template.name
+            );
+            assert_eq!(
+                rows.len(),
+                template.rows.split_whitespace().count(),
+                "{}",
+                template.name
+            );
+            // Areas are the first ones of `AREAS`, each a rectangle.
+            let mut names: Vec<&str> = rows.concat();
+            names.sort();
+            names.dedup();
+            assert_eq!(names, AREAS[..names.len()], "{}", template.name);
+            for name in names {
+                let cells: Vec<(usize, usize)> = (0..rows.len())
+                    .flat_map(|r| (0..columns).map(move |c| (r, c)))
+                    .filter(|(r, c)| rows[*r][*c] == name)
+                    .collect();
+                let (top, left) = cells[0];
+                let (bottom, right) = cells[cells.len() - 1];
+                assert_eq!(
+                    cells.len(),
+                    (bottom - top + 1) * (right - left + 1),
+                    "{}",
+                    template.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn templates_have_unique_names() {
+        let mut names: Vec<&str> = TEMPLATES.iter().map(|template| template.name).collect();
+        names.sort();
+        let len = names.len();
+        names.dedup();
+        assert_eq!(names.len(), len);
+    }
+
+    #[test]
+    fn every_element_gets_an_area() {
+        let elements: Vec<Element> = [Heading, Text, List, Image]
+            .into_iter()
+            .map(element)
+            .collect();
+        let html = render(None, &elements);
+        assert!(html.starts_with("<div class=\"layout layout-square\">"));
+        for (area, element) in AREAS.iter().zip(&elements) {
+            assert!(html.contains(&format!(
+                "<div class=\"area area-{}\">{}</div>",
+                area, element.content
+            )));
+        }
+        assert!(!html.contains("<style>"));
+    }
+
+    #[test]
+    fn named_headings_above() {
+        let elements: Vec<Element> = [Heading, Text, Image].into_iter().map(element).collect();
+        let html = render(Some(Layout::Sidebar), &elements);
+        assert!(html.starts_with("<heading><div class=\"layout layout-sidebar\">"));
+        assert!(html.contains(
+            "<div class=\"area area-a\"><text></div><div class=\"area area-b\"><image></div>"
+        ));
+    }
+
+    #[test]
+    fn past_the_areas() {
+        let elements: Vec<Element> = (0..5).map(|_| element(Text)).collect();
+        assert_eq!(render(None, &elements), "<text>".repeat(5));
+        let html = render(Some(Layout::Quadrants), &elements);
+        assert!(html.ends_with(
+            "<div class=\"area area-d\"><text></div><div class=\"area\"><text></div></div>"
+        ));
+    }
+
+    #[test]
+    fn scale_estimate() {
+        let canvas = Canvas::default();
+        let text = |words: usize| Element {
+            nature: Text,
+            content: String::new(),
+            source: vec!["word ".repeat(words)],
+            start: None,
+        };
+        assert_eq!(estimated_scale(None, &[text(10)], canvas), 1.0);
+        let long = estimated_scale(None, &[text(800)], canvas);
+        assert!(long < 1.0);
+        // Sharing the slide leaves less room.
+        let shared = estimated_scale(None, &[text(800), element(Image)], canvas);
+        assert!(shared < long);
+        assert_eq!(estimated_scale(None, &[element(Image)], canvas), 1.0);
+    }
+
+    #[test]
+    fn full_bleed_overlay() {
+        let elements: Vec<Element> = [Heading, Image, Text].into_iter().map(element).collect();
+        let html = render(Some(Layout::FullBleed), &elements);
+        assert_eq!(
+            html,
+            "<div class=\"layout layout-full-bleed\"><div class=\"area area-a\"><image></div><div class=\"area over\"><heading><text></div></div>"
+        );
+    }
+}
+
+//    This file is part of StultusVisio.
+//
+//    StultusVisio is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    any later version.
+//
+//    StultusVisio is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with StultusVisio.  If not, see <https://www.gnu.org/licenses/>.
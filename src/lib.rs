@@ -4,9 +4,15 @@
 // https://jeffersontorres.com.br
 
 //#![allow(unused)]
+pub mod export;
+pub mod layout;
+pub mod rules;
+
+pub use layout::Layout;
+pub use rules::Rule;
+
 use {
-    base64,
-    clap::Parser,
+    clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand, ValueEnum},
     core::panic,
     std::{
         fmt, fs,
@@ -17,10 +23,57 @@ use {
 };
 pub const COMMENT_MARKER: &str = "#";
 pub const STD_OUTPUT_FMT: &str = "html";
+pub const MARKDOWN_OUTPUT_FMT: &str = "md";
+pub const OUTLINE_OUTPUT_FMT: &str = "txt";
+pub const JSON_OUTPUT_FMT: &str = "json";
+pub const ASSETS_DIR: &str = "assets";
 pub const TAG_MARKER: &str = ".";
 pub const SEPARATOR: &str = "---";
 pub const TAG_FOOTER: &str = "footer";
 pub const TAG_LOGO: &str = "logo";
+pub const TAG_NOFOOTER: &str = "nofooter";
+pub const TAG_NOLOGO: &str = "nologo";
+pub const TAG_NUMBERING: &str = "numbering";
+pub const TAG_NONUMBER: &str = "nonumber";
+pub const TAG_TITLE: &str = "title";
+pub const TAG_AUTHOR: &str = "author";
+pub const TAG_DATE: &str = "date";
+pub const TAG_LANG: &str = "lang";
+pub const TAG_SUBTITLE: &str = "subtitle";
+pub const TAG_EVENT: &str = "event";
+pub const TAG_TITLESLIDE: &str = "titleslide";
+pub const TAG_SECTION: &str = "section";
+pub const TAG_AGENDA: &str = "agenda";
+pub const TAG_THEME: &str = "theme";
+pub const TAG_CSS: &str = "css";
+pub const TAG_JS: &str = "js";
+pub const TAG_FONT: &str = "font";
+pub const TAG_ASPECT: &str = "aspect";
+/// Tags that configure the whole presentation. A first block made only
+/// of them is a header rather than a slide.
+pub const DECK_TAGS: [&str; 21] = [
+    TAG_FIT,
+    TAG_OVERFLOW,
+    TAG_RULE,
+    TAG_RULES,
+    TAG_ORDER,
+    TAG_ASPECT,
+    TAG_FONT,
+    TAG_CSS,
+    TAG_JS,
+    TAG_THEME,
+    TAG_AGENDA,
+    TAG_TITLE,
+    TAG_SUBTITLE,
+    TAG_EVENT,
+    TAG_TITLESLIDE,
+    TAG_AUTHOR,
+    TAG_DATE,
+    TAG_LANG,
+    TAG_NUMBERING,
+    TAG_FOOTER,
+    TAG_LOGO,
+];
 pub const TAG_HEADING: &str = "heading";
 pub const TAG_SUBHEADING: &str = "subheading";
 pub const TAG_TABLE: &str = "table";
@@ -31,36 +84,107 @@ pub const TAG_MERMAID: &str = "mermaid";
 pub const TAG_MERMAIDSCRIPT: &str = "mermaidscript";
 pub const TAG_VIDEO: &str = "video";
 pub const TAG_IMAGE: &str = "image";
+pub const TAG_AUDIO: &str = "audio";
 pub const TAG_DRAFT: &str = "draft";
+pub const TAG_NOTES: &str = "notes";
+pub const TAG_LAYOUT: &str = "layout";
+pub const TAG_ORDER: &str = "order";
+pub const TAG_RULE: &str = "rule";
+pub const TAG_RULES: &str = "rules";
+pub const TAG_OVERFLOW: &str = "overflow";
+pub const TAG_FIT: &str = "fit";
+pub const TAG_ID: &str = "id";
 
 /// `Cli` from `Clap`.
 #[derive(Parser)]
 pub struct Cli {
     /// Points to a file as input.
-    #[arg(short, long)]
-    input: PathBuf,
+    #[arg(short, long, global = true)]
+    input: Option<PathBuf>,
 
     /// Defines the output file, no extension needed.
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     output: Option<PathBuf>,
+
+    /// Embed the media into the `HTML`, or write it to an `assets/`
+    /// directory next to the output, referenced by relative urls.
+    #[arg(long, value_enum, default_value_t = AssetMode::Embedded)]
+    pub assets: AssetMode,
+
+    /// A built-in theme (light, dark, high-contrast or corporate) or a
+    /// path to a `CSS` file. Takes over the `.theme` of the input.
+    #[arg(long)]
+    pub theme: Option<String>,
+
+    /// Aspect ratio of the slides, like 16:9, 4:3 or 16:10, or their
+    /// size in pixels, like 1024x768. Takes over the `.aspect` of the
+    /// input.
+    #[arg(long)]
+    pub aspect: Option<String>,
+
+    /// A `CSS` file inlined after the built-in one, after any `.css`
+    /// of the input. Can be repeated.
+    #[arg(long)]
+    pub css: Vec<PathBuf>,
+
+    /// A script inlined after the built-in one, after any `.js` of the
+    /// input. Can be repeated.
+    #[arg(long)]
+    pub js: Vec<PathBuf>,
     //#[arg(short, long, action = clap::ArgAction::SetTrue)]
     //verbose: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-/// Uses `Clap` to handle the output.
-pub fn output(content: HTML, args: Cli) -> io::Result<()> {
-    let output_path = match args.output {
-        Some(mut output) => {
-            output.set_extension(STD_OUTPUT_FMT);
-            output
-        }
-        None => {
-            let mut output = args.input.clone();
-            output.set_extension(STD_OUTPUT_FMT);
-            output
-        }
+/// Subcommands other than the default `HTML` compilation.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Writes another version of the presentation instead of the `HTML`.
+    Export(Export),
+}
+
+/// The formats available to `sxpres export`. Exactly one must be chosen.
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct Export {
+    /// Markdown handout, with tables and images kept as markdown.
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Plain text outline, one indented block per slide.
+    #[arg(long)]
+    pub outline: bool,
+
+    /// The parsed presentation as `JSON`, for external tooling.
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// How media files reach the final `HTML`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AssetMode {
+    /// Base64 data inside a single file.
+    Embedded,
+    /// Content-hashed files in an `assets/` directory.
+    External,
+}
+
+/// The output path, from `Clap`, with its `extension` enforced.
+pub fn output_path(extension: &str, args: &Cli) -> PathBuf {
+    let mut output_path = match (&args.output, &args.input) {
+        (Some(output), _) => output.clone(),
+        (None, Some(input)) => input.clone(),
+        (None, None) => panic!("Error: no arguments passed."),
     };
-    let mut file = File::create(output_path)?;
+    output_path.set_extension(extension);
+    output_path
+}
+
+/// Uses `Clap` to handle the output. The `extension` is always
+/// enforced on the output path.
+pub fn output<T: fmt::Display>(content: T, extension: &str, args: &Cli) -> io::Result<()> {
+    let mut file = File::create(output_path(extension, args))?;
     writeln!(file, "{}", content)?;
 
     Ok(())
@@ -68,30 +192,42 @@ pub fn output(content: HTML, args: Cli) -> io::Result<()> {
 
 /// Treats all the forms of input using `Clap`.
 pub fn input(args: &Cli) -> Result<Vec<String>, fmt::Error> {
-    if args.input.exists() && args.input.is_file() {
-        let file = match File::open(&args.input) {
-            Ok(file) => file,
-            Err(err) => panic!("Can't open file {}", err),
-        };
+    match &args.input {
+        Some(input) if input.exists() && input.is_file() => {
+            let file = match File::open(input) {
+                Ok(file) => file,
+                Err(err) => panic!("Can't open file {}", err),
+            };
 
-        let reader = io::BufReader::new(file);
-        let lines: Result<Vec<String>, io::Error> = reader.lines().collect();
+            let reader = io::BufReader::new(file);
+            let lines: Result<Vec<String>, io::Error> = reader.lines().collect();
 
-        match lines {
-            Ok(lines) => Ok(lines),
-            Err(err) => {
-                eprintln!("Error reading lines {}", err);
-                Err(fmt::Error)
+            match lines {
+                Ok(lines) => Ok(lines),
+                Err(err) => {
+                    eprintln!("Error reading lines {}", err);
+                    Err(fmt::Error)
+                }
             }
         }
-    } else {
-        panic!("Error: no arguments passed.")
+        Some(input) => {
+            eprintln!("Can't find the file {}.", input.display());
+            Err(fmt::Error)
+        }
+        // Checked here, since `Clap` can't have `input` both global
+        // and required.
+        None => Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "the argument '--input <INPUT>' is required",
+            )
+            .exit(),
     }
 }
 
 /// Define the nature of the elements. This can help organize the way
-/// multiple elements will be arranged. The order of declaration matters
-/// since that organize() uses sort_by_key() method.
+/// multiple elements will be arranged, see `layout::choose()`. The
+/// order of declaration is the one of `OrderRule::Nature`.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ElementNature {
     Heading,
@@ -100,15 +236,39 @@ pub enum ElementNature {
     OrdList,
     List,
     Video,
+    Audio,
     Image,
     Mermaid,
     Table,
 }
 impl ElementNature {
     fn is_heading_or_subheading(&self) -> bool {
+        matches!(self, ElementNature::Heading | ElementNature::Subheading)
+    }
+
+    fn is_media(&self) -> bool {
+        matches!(
+            self,
+            ElementNature::Image
+                | ElementNature::Video
+                | ElementNature::Audio
+                | ElementNature::Mermaid
+        )
+    }
+
+    /// The tag that produces an element of this nature.
+    pub fn tag(&self) -> &'static str {
         match self {
-            ElementNature::Heading | ElementNature::Subheading => true,
-            _ => false,
+            Self::Heading => TAG_HEADING,
+            Self::Subheading => TAG_SUBHEADING,
+            Self::Text => TAG_TEXT,
+            Self::OrdList => TAG_ORDLIST,
+            Self::List => TAG_ULIST,
+            Self::Video => TAG_VIDEO,
+            Self::Audio => TAG_AUDIO,
+            Self::Image => TAG_IMAGE,
+            Self::Mermaid => TAG_MERMAID,
+            Self::Table => TAG_TABLE,
         }
     }
 }
@@ -127,6 +287,7 @@ impl fmt::Display for ElementNature {
                 Self::Mermaid => "mermaid",
                 Self::Subheading => "subheading",
                 Self::Video => "video",
+                Self::Audio => "audio",
                 Self::Table => "table",
             }
         );
@@ -140,6 +301,23 @@ pub struct Element {
     /// Will be used to control Elements combinations.
     pub nature: ElementNature,
     pub content: String,
+    /// The lines that followed the tag, as written by the user. Lets
+    /// the exporters work over the parsed slides instead of the `HTML`.
+    pub source: Vec<String>,
+    /// Number of the first item of an ordered list going on from a
+    /// split one, see `rules::apply()`.
+    pub start: Option<usize>,
+}
+
+/// An element of the `nature` alone, for the tests of the modules.
+#[cfg(test)]
+fn element(nature: ElementNature) -> Element {
+    Element {
+        content: format!("<{}>", nature.tag()),
+        nature,
+        source: vec![],
+        start: None,
+    }
 }
 
 impl fmt::Display for Element {
@@ -153,30 +331,220 @@ impl fmt::Display for Element {
         write!(
             f,
             "content: \"{}\"... of nature:\"{}\"",
-            self.content[..20].to_string(),
+            &self.content[..20],
             nature
         );
         Ok(())
     }
 }
 
+/// A rule of `Organize`, moving some elements ahead of the others.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderRule {
+    /// The heading, then the subheading, on top.
+    HeadingsFirst,
+    /// Images, videos, audios and diagrams before the other elements.
+    MediaFirst,
+    /// Images, videos, audios and diagrams after the other elements.
+    MediaLast,
+    /// By `ElementNature`, as `SxPres` used to order every slide.
+    Nature,
+}
+
+impl OrderRule {
+    pub const ALL: [OrderRule; 4] = [
+        OrderRule::HeadingsFirst,
+        OrderRule::MediaFirst,
+        OrderRule::MediaLast,
+        OrderRule::Nature,
+    ];
+
+    /// Name given to `.order`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::HeadingsFirst => "headings-first",
+            Self::MediaFirst => "media-first",
+            Self::MediaLast => "media-last",
+            Self::Nature => "nature",
+        }
+    }
+
+    /// Rank of an element under the rule, lowest first.
+    fn rank(&self, nature: &ElementNature) -> usize {
+        match self {
+            Self::HeadingsFirst => match nature {
+                ElementNature::Heading => 0,
+                ElementNature::Subheading => 1,
+                _ => 2,
+            },
+            Self::MediaFirst => usize::from(!nature.is_media()),
+            Self::MediaLast => usize::from(nature.is_media()),
+            Self::Nature => nature.clone() as usize,
+        }
+    }
+}
+
+/// The rules when no `.order` is given: headings on top, the other
+/// elements as written.
+pub const DEFAULT_ORDER: [OrderRule; 1] = [OrderRule::HeadingsFirst];
+
+/// Read an `.order`, a list of `OrderRule` names applied one after the
+/// other. `.order source` keeps the elements as written.
+pub fn order(raw_element: Vec<String>) -> Result<Vec<OrderRule>, fmt::Error> {
+    is_tag_ok(&raw_element, TAG_ORDER)?;
+    let mut rules = vec![];
+    for arg in raw_element.tag_args() {
+        match OrderRule::ALL.into_iter().find(|rule| rule.name() == arg) {
+            Some(rule) => rules.push(rule),
+            None if arg == "source" => (),
+            None => return Err(invalid_argument(arg, &raw_element)),
+        }
+    }
+    Ok(rules)
+}
+
+/// Read a `.rule name limit [action]`, like `.rule max-words 40 split`.
+/// The action defaults to `warn`, and `.rule name off` needs no limit.
+pub fn rule(raw_element: Vec<String>) -> Result<Rule, fmt::Error> {
+    use rules::{Action, Check};
+    is_tag_ok(&raw_element, TAG_RULE)?;
+    let args = raw_element.tag_args();
+    let check = match args.first() {
+        Some(name) => match Check::ALL.into_iter().find(|check| check.name() == *name) {
+            Some(check) => check,
+            None => return Err(invalid_argument(name, &raw_element)),
+        },
+        None => {
+            eprintln!("The \"{}\" misses the name of the rule.", raw_element[0]);
+            return Err(fmt::Error);
+        }
+    };
+    let (limit, action) = match &args[1..] {
+        ["off"] => ("0", Action::Off),
+        [limit] => (*limit, Action::Warn),
+        [limit, action] => match Action::ALL
+            .into_iter()
+            .find(|other| other.name() == *action)
+        {
+            Some(action) if action != Action::Off => (*limit, action),
+            _ => return Err(invalid_argument(action, &raw_element)),
+        },
+        _ => {
+            eprintln!(
+                "The \"{}\" needs a limit, and maybe an action.",
+                raw_element[0]
+            );
+            return Err(fmt::Error);
+        }
+    };
+    // Splitting at zero would never end, and a text or list can't be
+    // left empty.
+    let shrinks = matches!(check, Check::MaxWords | Check::MaxListItems);
+    let limit = match (limit.parse::<usize>(), action) {
+        (Ok(0), Action::Split) => return Err(invalid_argument(limit, &raw_element)),
+        (Ok(0), Action::Drop) if shrinks => return Err(invalid_argument(limit, &raw_element)),
+        (Ok(limit), _) => limit,
+        (Err(_), _) => return Err(invalid_argument(limit, &raw_element)),
+    };
+    Ok(Rule {
+        check,
+        limit,
+        action,
+    })
+}
+
+/// Read an `.overflow split [items]`, the opt-in for continuation
+/// slides: the elements past the `max-elements` limit, and the list
+/// items past `items` if given, go to the next slide rather than being
+/// left out. `.overflow drop` goes back to leaving them out.
+pub fn overflow(raw_element: Vec<String>, current: &[Rule]) -> Result<Vec<Rule>, fmt::Error> {
+    use rules::{Action, Check, DEFAULT_RULES};
+    is_tag_ok(&raw_element, TAG_OVERFLOW)?;
+    let limit = current
+        .iter()
+        .chain(&DEFAULT_RULES)
+        .find(|rule| rule.check == Check::MaxElements && rule.action != Action::Off)
+        .map(|rule| rule.limit)
+        .unwrap_or_default();
+    let rule = |check, limit, action| Rule {
+        check,
+        limit,
+        action,
+    };
+    match raw_element.tag_args()[..] {
+        ["split"] => Ok(vec![rule(Check::MaxElements, limit, Action::Split)]),
+        ["split", items] => match items.parse::<usize>() {
+            Ok(items) if items > 0 => Ok(vec![
+                rule(Check::MaxElements, limit, Action::Split),
+                rule(Check::MaxListItems, items, Action::Split),
+            ]),
+            _ => Err(invalid_argument(items, &raw_element)),
+        },
+        ["drop"] => Ok(vec![rule(Check::MaxElements, limit, Action::Drop)]),
+        _ => {
+            eprintln!(
+                "The \"{}\" takes \"split\", maybe with a number of list items, or \"drop\".",
+                raw_element[0]
+            );
+            Err(fmt::Error)
+        }
+    }
+}
+
+/// Read the rules of a `.rules` file, one `.rule` per line without the
+/// tag, like `max-words 40 split`. Lines starting with `#` are comments.
+pub fn rules_file(path: &str) -> Result<Vec<Rule>, fmt::Error> {
+    let content = fs::read_to_string(path).map_err(|err| {
+        eprintln!("Can't read the rules file {}: {}", path, err);
+        fmt::Error
+    })?;
+    content
+        .lines()
+        .map(|line| line.to_string())
+        .filter(|line| !line.trim().is_empty() && !line.is_comment())
+        .map(|line| rule(vec![format!("{}{} {}", TAG_MARKER, TAG_RULE, line)].clean_tag()))
+        .collect()
+}
+
 /// Makes sure that priorities between `Elements` are respected.
 pub trait Organize {
-    fn organize(self) -> Self;
+    fn organize(self, rules: &[OrderRule]) -> Self;
 }
 
 impl Organize for Vec<Element> {
-    // organize() just makes sure that the priority order of Elements
-    // are respected.
-    fn organize(mut self) -> Self {
-        // Must allow empty Slide with no Elements.
-        if self.len() < 1 {
-            self
-        } else {
-            // Cloning here is ok since Element::Nature is a
-            // unit type enum.
-            self.sort_by_key(|e| e.nature.clone());
-            self
+    // organize() applies the rules in turn, the elements they leave
+    // tied keeping their source order, since sort_by_key() is stable.
+    fn organize(mut self, rules: &[OrderRule]) -> Self {
+        self.sort_by_key(|e| {
+            rules
+                .iter()
+                .map(|rule| rule.rank(&e.nature))
+                .collect::<Vec<usize>>()
+        });
+        self
+    }
+}
+
+/// What a slide is for, when it is not made of the user elements only.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlideKind {
+    Content,
+    /// Generated by `.titleslide` from the `Metadata`.
+    Title,
+    /// Divider opening a `.section`.
+    Section,
+    /// Generated by `.agenda` from the sections.
+    Agenda,
+}
+
+impl SlideKind {
+    /// Name used by the exports.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Content => "content",
+            Self::Title => "title",
+            Self::Section => "section",
+            Self::Agenda => "agenda",
         }
     }
 }
@@ -185,9 +553,58 @@ impl Organize for Vec<Element> {
 /// a `<div class=slide>` that will be formatted by `CSS` to fill
 /// the screen and respect the `Javascript` controls.
 pub struct Slide {
-    //pub number: usize, // NEEDED??
+    pub kind: SlideKind,
+    /// Position during the presentation, none for drafts.
+    pub number: Option<usize>,
+    /// False on `.nonumber` slides.
+    pub numbered: bool,
+    /// The visible number, as asked by `.numbering`.
+    pub label: Option<String>,
     pub content: Result<Vec<Element>, fmt::Error>,
     pub draft: bool,
+    /// Speaker notes, never rendered on the slide itself.
+    pub notes: Vec<String>,
+    /// An `.audio background`, played hidden behind the elements.
+    pub background: Option<Element>,
+    /// Foot message, from the last `.footer` up to this slide.
+    pub footer: Option<String>,
+    /// Logomark, from the last `.logo` up to this slide.
+    pub logo: Option<Logo>,
+    /// Name of the last `.section` up to this slide.
+    pub section: Option<String>,
+    /// Asked by `.layout`, otherwise chosen from the elements.
+    pub layout: Option<Layout>,
+    /// Stable name given by `.id`, linked to as `#/name`.
+    pub id: Option<String>,
+}
+
+impl Slide {
+    /// A slide built by `SxPres` itself rather than from the input.
+    fn generated(kind: SlideKind, elements: Vec<Element>) -> Self {
+        Slide {
+            kind,
+            number: None,
+            numbered: true,
+            label: None,
+            content: Ok(elements),
+            draft: false,
+            notes: vec![],
+            background: None,
+            footer: None,
+            logo: None,
+            section: None,
+            layout: None,
+            id: None,
+        }
+    }
+}
+
+/// A text made safe to put between the quotes of an HTML attribute.
+fn attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
 }
 
 impl fmt::Display for Slide {
@@ -195,94 +612,32 @@ impl fmt::Display for Slide {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut before = String::new();
 
-        match self.draft {
-            false => before = "<div class=\"slide\">".to_string(),
-            true => before = "<div class=\"slide, draft-slide\">".to_string(),
+        let mut section = match &self.section {
+            Some(section) => format!(" data-section=\"{}\"", attribute(section)),
+            None => String::new(),
         };
+        if let Some(id) = &self.id {
+            section += &format!(" data-id=\"{}\"", attribute(id));
+        }
 
-        let mut css = String::new();
-        let mut frames = String::new();
-        let mut fill = String::new();
-
-        fill = match &self.content {
-            Ok(elements) => match elements.len() {
-                // TODO: PASSAR TODOS STYLES PARA LITERAL NO CORPO DOS DIVS
-                0 => {
-                    format!("")
-                }
-                1 => {
-                    format!(
-                        "<div style=margin-top:auto;margin-bottom:auto>{}</div>",
-                        &elements[0].content
-                    )
-                }
-                2 => {
-                    if elements[0].nature.is_heading_or_subheading() {
-                        format!(
-                            "<div style=\"margin-top:auto;margin-bottom:auto;display:grid\">
-                            <div style=\"height: 30%;\">{}</div>
-                            <div style=\"height: 30%;\">{}</div>
-                            </div>",
-                            &elements[0].content, &elements[1].content
-                        )
-                    } else {
-                        format!(
-                            "<div style=\"display:inline-flex;justify-content:center;width:100%;max-height:90%;margin-top:auto;margin-bottom:auto\">
-                            <div style=\"width:100%;height:100%\">{}</div>
-                            <div class=frame>{}</div>
-                            </div>",
-                            &elements[0].content, &elements[1].content
-                        )
-                    }
-                }
-                3 => {
-                    // single three elements column
-                    if elements[2].nature.is_heading_or_subheading()
-                        | elements[1].nature.is_heading_or_subheading()
-                    {
-                        format!(
-                            "<div>
-                            <div style=\"height:29% !important;\" class=frame>{}</div>
-                            <div style=\"height:29% !important;\" class=frame>{}</div>
-                            <div style=\"height:29% !important;\" class=frame>{}</div>
-                            </div>",
-                            &elements[0].content, &elements[1].content, &elements[2].content
-                        )
-                    } else {
-                        // pyramid format
-                        format!(
-                            "<div style=\"height:49vh !important;\">
-                                <div>{}</div>
-                            </div>
-                            <div style=\"display:inline-flex;justify-content:center;width:80vw; height:49vh !important\">
-                                <div>{}</div>
-                                <div>{}</div>
-                            </div>",
-                            &elements[0].content, &elements[1].content, &elements[2].content
-                        )
-                    }
-                }
-                4 => {
-                    // square format
-                    css = format!("<style></style>");
-                    format!(
-                        "{}<div>
-                            <div>{}</div>
-                            <div>{}</div>
-                        </div>
-                        <div>
-                            <div>{}</div>
-                            <div>{}</div>
-                        </div>",
-                        css,
-                        &elements[0].content,
-                        &elements[1].content,
-                        &elements[2].content,
-                        &elements[3].content
-                    )
-                }
-                5.. => String::from("este slide ficou em branco pq passou de 4 elementos?"),
-            },
+        match (self.draft, self.kind) {
+            (false, SlideKind::Content) => before = format!("<div class=\"slide\"{}>", section),
+            (false, kind) => {
+                before = format!("<div class=\"slide {}-slide\"{}>", kind.name(), section)
+            }
+            (true, _) => before = "<div class=\"slide, draft-slide\">".to_string(),
+        };
+
+        let fill = match &self.content {
+            // Dedicated layout, all elements stacked and centered.
+            Ok(elements) if self.kind == SlideKind::Title => format!(
+                "<div class=\"title-layout\">{}</div>",
+                elements
+                    .iter()
+                    .map(|element| element.content.as_str())
+                    .collect::<String>()
+            ),
+            Ok(elements) => layout::render(self.layout, elements),
             Err(_) => String::from("este slide deu erro?"),
         };
         // match &self.content {
@@ -294,22 +649,71 @@ impl fmt::Display for Slide {
         //     Err(_) => (),
         // };
 
-        write!(f, "{}{}</div>", before, fill);
+        let background = match &self.background {
+            Some(audio) => audio.content.as_str(),
+            None => "",
+        };
+
+        let foot = match &self.footer {
+            Some(foot) => format!("<footer>{}</footer>", foot),
+            None => String::new(),
+        };
+
+        let logo_img = match &self.logo {
+            Some(logo) => logo.content.as_str(),
+            None => "",
+        };
+
+        let label = match &self.label {
+            Some(label) => format!("<span class=\"slide-number\">{}</span>", label),
+            None => String::new(),
+        };
+
+        write!(
+            f,
+            "{}{}{}{}{}{}</div>",
+            before, logo_img, fill, background, foot, label
+        );
         Ok(())
     }
 }
 
+/// Read the `.id` of a slide: letters, digits, `-` and `_`, not only
+/// digits, so it can't be taken for a slide number.
+pub fn id(raw_element: Vec<String>) -> Result<String, fmt::Error> {
+    let id = directive(raw_element.clone(), TAG_ID)?;
+    let allowed = |c: char| c.is_alphanumeric() || c == '-' || c == '_';
+    if id.chars().all(allowed) && !id.chars().all(|c| c.is_ascii_digit()) {
+        Ok(id)
+    } else {
+        Err(invalid_argument(&id, &raw_element))
+    }
+}
+
+/// Read the `.layout` of a slide.
+pub fn layout(raw_element: Vec<String>) -> Result<Layout, fmt::Error> {
+    let name = directive(raw_element, TAG_LAYOUT)?;
+    match Layout::ALL.into_iter().find(|layout| layout.name() == name) {
+        Some(layout) => Ok(layout),
+        None => {
+            let names: Vec<&str> = Layout::ALL.iter().map(|layout| layout.name()).collect();
+            eprintln!(
+                "The layout \"{}\" is not one of {}.",
+                name,
+                names.join(", ")
+            );
+            Err(fmt::Error)
+        }
+    }
+}
+
 /// Recognise a comment line.
 pub trait IsComment {
     fn is_comment(&self) -> bool;
 }
 impl IsComment for String {
     fn is_comment(&self) -> bool {
-        if self.starts_with(COMMENT_MARKER) {
-            true
-        } else {
-            false
-        }
+        self.starts_with(COMMENT_MARKER)
     }
 }
 
@@ -344,49 +748,143 @@ impl SplitOnTag for Vec<String> {
     }
 }
 
-/// Safely parsing tags before processing.
-///
-/// TODO: In the future, CleanTag should be capable
-/// of doing more complex tag manipulation also.
+/// Safely parsing tags before processing. The tag line is normalised
+/// to the marker, the tag and its arguments separated by single spaces,
+/// so `.  video   loop` becomes `.video loop`.
 pub trait CleanTag {
     fn clean_tag(self) -> Self;
 }
 impl CleanTag for Vec<String> {
     fn clean_tag(mut self) -> Self {
-        self[0] = self[0].replace(" ", "");
+        let words: Vec<&str> = self[0]
+            .trim_start()
+            .trim_start_matches(TAG_MARKER)
+            .split_whitespace()
+            .collect();
+        self[0] = format!("{}{}", TAG_MARKER, words.join(" "));
         self
     }
 }
 
+/// Reads the tag line of a `raw_element` that went through `CleanTag`.
+pub trait ReadTag {
+    /// The tag, without the marker.
+    fn tag(&self) -> &str;
+    /// The arguments passed on the tag line, after the tag.
+    fn tag_args(&self) -> Vec<&str>;
+}
+impl ReadTag for [String] {
+    fn tag(&self) -> &str {
+        self[0][TAG_MARKER.len()..]
+            .split(' ')
+            .next()
+            .unwrap_or_default()
+    }
+    fn tag_args(&self) -> Vec<&str> {
+        self[0].split(' ').skip(1).collect()
+    }
+}
+
 /// Convert external files into raw base64 data to be embedded into
 /// the final `HTML`.
 pub fn file_base64(file: String, tipo: &str) -> Result<String, fmt::Error> {
-    let file_data = fs::read(&file).expect("Media file passed to file_base64() not found.");
+    let file_data = read_media(&file)?;
 
     Ok(format!(
         "data:{}/{};base64,{}",
         tipo,
         Path::new(&file)
             .extension()
-            .expect(&format!("Error trying to set the filetype of {}", &file))
+            .unwrap_or_else(|| panic!("Error trying to set the filetype of {}", &file))
             .to_str()
             .ok_or(&format!("Error converting the path {} to string.", &file))
-            .expect(&format!("Error trying validate {} as a file path.", &file)),
+            .unwrap_or_else(|_| panic!("Error trying validate {} as a file path.", &file)),
         base64::encode(&file_data)
     ))
 }
 
+/// Where `media()` sends the files referenced by the slides.
+pub enum Assets {
+    /// Embedded with `file_base64()`, the single-file philosophy.
+    Embedded,
+    /// Copied into the directory, named after their content so that
+    /// repeated media is written once and caches never go stale.
+    External(PathBuf),
+}
+
+impl Assets {
+    /// The `Assets` asked for through `Clap`, external ones living in
+    /// `ASSETS_DIR` next to the `HTML` output.
+    pub fn from_cli(args: &Cli) -> Self {
+        match args.assets {
+            AssetMode::Embedded => Assets::Embedded,
+            AssetMode::External => {
+                let output = output_path(STD_OUTPUT_FMT, args);
+                let parent = output.parent().unwrap_or(Path::new(""));
+                Assets::External(parent.join(ASSETS_DIR))
+            }
+        }
+    }
+}
+
+/// The bytes of a media file of the input.
+fn read_media(file: &str) -> Result<Vec<u8>, fmt::Error> {
+    fs::read(file).map_err(|err| {
+        eprintln!("Can't read the media file {}: {}", file, err);
+        fmt::Error
+    })
+}
+
+/// 64 bits FNV-1a, stable across builds, which `std` hashers are not.
+fn content_hash(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The `src` of a media file, either embedded or copied to the
+/// external assets directory.
+pub fn media(file: String, tipo: &str, assets: &Assets) -> Result<String, fmt::Error> {
+    match assets {
+        Assets::Embedded => file_base64(file, tipo),
+        Assets::External(dir) => {
+            let file_data = read_media(&file)?;
+            let name = match Path::new(&file).extension().and_then(|ext| ext.to_str()) {
+                Some(ext) => format!("{:016x}.{}", content_hash(&file_data), ext),
+                None => format!("{:016x}", content_hash(&file_data)),
+            };
+            let path = dir.join(&name);
+            if !path.exists() {
+                fs::create_dir_all(dir)
+                    .and_then(|_| fs::write(&path, &file_data))
+                    .map_err(|err| {
+                        eprintln!("Can't write the asset {}: {}", path.display(), err);
+                        fmt::Error
+                    })?;
+            }
+            Ok(format!("{}/{}", ASSETS_DIR, name))
+        }
+    }
+}
+
 /// Do all the checks necessary to validate a `raw_element` as `Element`.
-pub fn is_element_ok(raw_element: &Vec<String>, reference: &str) -> Result<(), fmt::Error> {
+pub fn is_element_ok(raw_element: &[String], reference: &str) -> Result<(), fmt::Error> {
     if raw_element.len() < 2 {
         eprintln!("A tag {} was not followed by its argument.", raw_element[0]); // Improve error msg to point where?
         Err(fmt::Error)
-    } else if !(*raw_element[0] == format!("{}{}", TAG_MARKER, reference)) {
-        eprintln!("The tag \"{}\" is not valid.", raw_element[0]);
-        Err(fmt::Error)
     } else {
-        Ok(())
+        is_tag_ok(raw_element, reference)
+    }
+}
+
+/// Check that `raw_element` is the tag `reference`, its arguments
+/// being left to the caller.
+pub fn is_tag_ok(raw_element: &[String], reference: &str) -> Result<(), fmt::Error> {
+    if raw_element.tag() != reference {
+        eprintln!("The tag \"{}\" is not valid.", raw_element[0]);
+        return Err(fmt::Error);
     }
+    Ok(())
 }
 
 /// The `<p>` rendering function.
@@ -399,6 +897,8 @@ pub fn text(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     }
     Ok(Element {
         nature: ElementNature::Text,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: p + "</p></div>",
     })
 }
@@ -414,6 +914,8 @@ pub fn heading(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     );
     Ok(Element {
         nature: ElementNature::Heading,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: heading,
     })
 }
@@ -425,26 +927,144 @@ pub fn subheading(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     let subheading = format!("<div class=\"element\"><h2>{}</h2></div>", raw_element[1]);
     Ok(Element {
         nature: ElementNature::Subheading,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: subheading,
     })
 }
 
-/// The `<video>` rendering function.
-pub fn video(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
+/// Report an argument that a tag doesn't know about.
+fn invalid_argument(arg: &str, raw_element: &[String]) -> fmt::Error {
+    eprintln!(
+        "The argument \"{}\" of \"{}\" is not valid.",
+        arg, raw_element[0]
+    );
+    fmt::Error
+}
+
+/// Seconds from a `ss`, `mm:ss` or `hh:mm:ss` time.
+fn seconds(time: &str) -> Option<f64> {
+    time.split(':').try_fold(0.0, |total, part| {
+        part.parse::<f64>()
+            .ok()
+            .filter(|part| part.is_finite() && *part >= 0.0)
+            .map(|part| total * 60.0 + part)
+    })
+}
+
+/// The `<video>` rendering function. The tag accepts `autoplay`, muted
+/// as browsers demand, to start playing as the slide is shown, `loop`,
+/// `muted`, `poster=image.jpg` and the `start=1:05` and `end=1:30`
+/// clipping. Videos are paused once their slide is left.
+pub fn video(raw_element: Vec<String>, assets: &Assets) -> Result<Element, fmt::Error> {
     // Ignores info passed beyond raw_element[1].
     is_element_ok(&raw_element, TAG_VIDEO)?;
+    let mut attributes = String::new();
+    let mut muted = false;
+    let (mut start, mut end) = (None, None);
+    for arg in raw_element.tag_args() {
+        match arg.split_once('=') {
+            None if arg == "autoplay" => {
+                attributes += " data-autoplay";
+                muted = true;
+            }
+            None if arg == "loop" => attributes += " loop",
+            None if arg == "muted" => muted = true,
+            Some(("poster", poster)) => {
+                attributes += &format!(
+                    " poster=\"{}\"",
+                    media(poster.to_string(), "image", assets)?
+                )
+            }
+            Some(("start", time)) => {
+                start = Some(seconds(time).ok_or_else(|| invalid_argument(arg, &raw_element))?)
+            }
+            Some(("end", time)) => {
+                let time = seconds(time).ok_or_else(|| invalid_argument(arg, &raw_element))?;
+                end = Some((time, arg));
+            }
+            _ => return Err(invalid_argument(arg, &raw_element)),
+        }
+    }
+    if muted {
+        attributes += " muted";
+    }
+    // A clip ending before it starts would never play.
+    let end = match end {
+        Some((end, arg)) if end <= start.unwrap_or(0.0) => {
+            return Err(invalid_argument(arg, &raw_element))
+        }
+        end => end.map(|(end, _)| end),
+    };
+    // The media fragment clips the first play, the `data-` attributes
+    // let `script.js` keep to it on the next ones.
+    let fragment = match (start, end) {
+        (None, None) => String::new(),
+        (start, end) => {
+            let start = start.unwrap_or(0.0);
+            attributes += &format!(" data-start=\"{}\"", start);
+            match end {
+                Some(end) => {
+                    attributes += &format!(" data-end=\"{}\"", end);
+                    format!("#t={},{}", start, end)
+                }
+                None => format!("#t={}", start),
+            }
+        }
+    };
     let video_path: String = raw_element[1].clone();
-    let video = file_base64(video_path, "video");
+    let video = media(video_path, "video", assets);
     let vid_content = format!(
-        "<div class=element><video controls src=\"{}\"></video></div>",
-        video?
+        "<div class=element><video controls{} src=\"{}{}\"></video></div>",
+        attributes, video?, fragment
     );
     Ok(Element {
         nature: ElementNature::Video,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: vid_content,
     })
 }
 
+/// The `<audio>` rendering function. The tag accepts `autoplay`, to
+/// start playing as the slide is shown, `loop`, and `stop`, to stop and
+/// rewind once the slide is left. A `background` audio autoplays and
+/// stops with its slide, with no controls, and is kept apart from the
+/// elements by `parse()`.
+pub fn audio(raw_element: Vec<String>, assets: &Assets) -> Result<Element, fmt::Error> {
+    // Ignores info passed beyond raw_element[1].
+    is_element_ok(&raw_element, TAG_AUDIO)?;
+    let mut attributes = String::new();
+    let mut controls = " controls";
+    let mut stop = "";
+    for arg in raw_element.tag_args() {
+        match arg {
+            // Not the `autoplay` attribute, that would start all the
+            // audios of the presentation on load.
+            "autoplay" => attributes += " data-autoplay",
+            "loop" => attributes += " loop",
+            "stop" => stop = " data-stop",
+            "background" => {
+                attributes += " data-autoplay";
+                stop = " data-stop";
+                controls = "";
+            }
+            _ => return Err(invalid_argument(arg, &raw_element)),
+        }
+    }
+    let audio = media(raw_element[1].clone(), "audio", assets);
+    let content = format!(
+        "<div class=element><audio{}{}{} src=\"{}\"></audio></div>",
+        controls, attributes, stop, audio?
+    );
+    Ok(Element {
+        nature: ElementNature::Audio,
+        source: raw_element[1..].to_vec(),
+        start: None,
+        content,
+    })
+}
+
 /// The `<table>` rendering function.
 pub fn table(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     is_element_ok(&raw_element, TAG_TABLE)?;
@@ -462,50 +1082,106 @@ pub fn table(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     table += "</table></div>";
     Ok(Element {
         nature: ElementNature::Table,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: table,
     })
 }
 
-/// Render a `<footer>` element with a foot message on all slides.
+/// Read the foot message shown from this slide onwards, on the next
+/// line or right after the tag. It is rendered to a `<footer>` element
+/// by each `Slide`.
 pub fn footer(raw_element: Vec<String>) -> Result<String, fmt::Error> {
-    is_element_ok(&raw_element, TAG_FOOTER)?;
-    Ok(format!("<footer>{}</footer>", raw_element[1]))
+    directive(raw_element, TAG_FOOTER)
 }
 
-/// Render a `<image class=logo>` that is treated in `CSS` as
-/// a fixed right top logomark to the slides.
-///
-/// TODO: 1) Make the option to change the logo from a new definition
-/// onwards; and 2) Make the option to choose the position.
-pub fn logo(raw_element: Vec<String>) -> Result<String, fmt::Error> {
-    is_element_ok(&raw_element, TAG_LOGO)?;
-    let image = file_base64(raw_element[1].clone(), "image");
-    Ok(format!("<img class=\"logo\" src=\"{}\"></img>", image?))
+/// A logomark, resolved once and repeated over the slides.
+#[derive(Clone)]
+pub struct Logo {
+    /// Path as written in the input.
+    pub path: String,
+    /// The rendered `<img class=logo>`.
+    pub content: String,
+}
+
+/// Corners the logomark can be placed on, the first one by default.
+pub const LOGO_POSITIONS: [&str; 4] = ["top-right", "top-left", "bottom-right", "bottom-left"];
+
+/// A `CSS` length from a `.logo` size, percents if no unit is given.
+fn logo_size(size: &str) -> Option<String> {
+    let unit_at = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(unit_at);
+    number.parse::<f64>().ok()?;
+    match unit {
+        "" => Some(format!("{}%", number)),
+        "%" | "px" | "em" | "rem" | "vw" | "vh" => Some(size.to_string()),
+        _ => None,
+    }
+}
+
+/// Render a `<image class=logo>` that is treated in `CSS` as a fixed
+/// logomark to the slides, from this slide onwards. The path goes on
+/// the next line or right after the tag, followed by any of the
+/// `LOGO_POSITIONS` and a size, like `.logo logo.png bottom-left 12%`.
+pub fn logo(raw_element: Vec<String>, assets: &Assets) -> Result<Logo, fmt::Error> {
+    let mut args = raw_element.tag_args();
+    let path = match raw_element.get(1) {
+        Some(path) => path.clone(),
+        None if !args.is_empty() => args.remove(0).to_string(),
+        None => {
+            eprintln!("A tag {} was not followed by its argument.", raw_element[0]);
+            return Err(fmt::Error);
+        }
+    };
+    is_tag_ok(&raw_element, TAG_LOGO)?;
+    let mut position = LOGO_POSITIONS[0];
+    let mut style = String::new();
+    for arg in args {
+        match LOGO_POSITIONS.iter().find(|corner| **corner == arg) {
+            Some(corner) => position = corner,
+            None => match logo_size(arg) {
+                Some(size) => style = format!(" style=\"width:{}\"", size),
+                None => return Err(invalid_argument(arg, &raw_element)),
+            },
+        }
+    }
+    let image = media(path.clone(), "image", assets);
+    Ok(Logo {
+        content: format!(
+            "<img class=\"logo logo-{}\"{} src=\"{}\"></img>",
+            position, style, image?
+        ),
+        path,
+    })
 }
 
 /// The `<image>` rendering function.
 /// Captions of figures are buided from the third line fowards
 /// on the raw_element.
-pub fn image(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
+pub fn image(raw_element: Vec<String>, assets: &Assets) -> Result<Element, fmt::Error> {
     is_element_ok(&raw_element, TAG_IMAGE)?;
     let image_path: String = raw_element[1].clone();
-    let image = file_base64(image_path, "image");
+    let image = media(image_path, "image", assets);
     let mut _content = format!("<div class=\"element\"><div><img src=\"{}\">", image?);
 
     // To treat captions...
     if raw_element.len() > 2 {
         let mut captions = String::from("<figcaption>");
-        captions = captions + &format!("{}", raw_element[2]);
+        captions += &raw_element[2];
         for line in &raw_element[3..] {
             captions = captions + &format!("<br>{}", line);
         }
         _content = _content + &captions + "</figcaption></img></div></div>";
     } else {
-        _content = _content + "</img></div></div>";
+        _content += "</img></div></div>";
     };
 
     Ok(Element {
         nature: ElementNature::Image,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: _content,
     })
 }
@@ -516,10 +1192,12 @@ pub fn mermaid(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     is_element_ok(&raw_element, TAG_MERMAID)?;
     let mut content = String::new();
     for raw_line in &raw_element[1..] {
-        content = content + &format!("{}", raw_line);
+        content += raw_line;
     }
     Ok(Element {
         nature: ElementNature::Mermaid,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: format!(
             "<div class=\"element\"><pre class=\"mermaid\">{}</pre></div>",
             &content
@@ -536,23 +1214,42 @@ pub fn ulist(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
     }
     Ok(Element {
         nature: ElementNature::List,
+        source: raw_element[1..].to_vec(),
+        start: None,
         content: out + "</ul></div>",
     })
 }
 
 /// Generate an `<ol>` style listing.
 pub fn ordlist(raw_element: Vec<String>) -> Result<Element, fmt::Error> {
+    ordlist_from(raw_element, 1)
+}
+
+/// An ordered list numbered from `start`.
+pub fn ordlist_from(raw_element: Vec<String>, start: usize) -> Result<Element, fmt::Error> {
     is_element_ok(&raw_element, TAG_ORDLIST)?;
-    let mut out = "<div class=\"element\"><ol>".to_string();
+    let mut out = match start {
+        1 => "<div class=\"element\"><ol>".to_string(),
+        _ => format!("<div class=\"element\"><ol start=\"{}\">", start),
+    };
     for raw_line in &raw_element[1..] {
         out = out + &format!("<li>{}</li>", raw_line);
     }
     Ok(Element {
         nature: ElementNature::OrdList,
+        source: raw_element[1..].to_vec(),
+        start: Some(start).filter(|start| *start > 1),
         content: out + "</ol></div>",
     })
 }
 
+/// Collect the speaker notes of a slide. They only reach the exports,
+/// the `HTML` slide is kept clean.
+pub fn notes(raw_element: Vec<String>) -> Result<Vec<String>, fmt::Error> {
+    is_element_ok(&raw_element, TAG_NOTES)?;
+    Ok(raw_element[1..].to_vec())
+}
+
 /// The final HTML.
 pub struct HTML(pub String);
 impl fmt::Display for HTML {
@@ -561,24 +1258,893 @@ impl fmt::Display for HTML {
     }
 }
 
-/// Finally condense back a `Vec<Slide>` into `HTML` that can be
+/// How `.numbering` shows the slide numbers. Drafts are never
+/// numbered, nor counted.
+pub struct Numbering {
+    /// `3/27` rather than `3`.
+    pub total: bool,
+    /// Leaves the title slide of `.titleslide` without its number.
+    pub skip_title: bool,
+}
+
+/// Read the `.numbering` of the presentation. It takes `plain`, the
+/// default, or `total` as format and `skip-title`.
+pub fn numbering(raw_element: Vec<String>) -> Result<Numbering, fmt::Error> {
+    is_tag_ok(&raw_element, TAG_NUMBERING)?;
+    let mut numbering = Numbering {
+        total: false,
+        skip_title: false,
+    };
+    for arg in raw_element.tag_args() {
+        match arg {
+            "plain" => numbering.total = false,
+            "total" => numbering.total = true,
+            "skip-title" => numbering.skip_title = true,
+            _ => return Err(invalid_argument(arg, &raw_element)),
+        }
+    }
+    Ok(numbering)
+}
+
+/// Width of the canvas when only its aspect ratio is given.
+pub const CANVAS_WIDTH: u32 = 1280;
+
+/// The fixed size the slides are laid out on, whatever the screen. The
+/// canvas is scaled as a whole to fit the window, leaving bars on the
+/// sides that don't match, and gives the page size when printing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Canvas {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Canvas {
+    /// 16:9.
+    fn default() -> Self {
+        Canvas {
+            width: CANVAS_WIDTH,
+            height: 720,
+        }
+    }
+}
+
+/// Read an aspect ratio, like `16:9`, `4:3` or `16:10`, or a size in
+/// pixels, like `1024x768`, into a `Canvas`.
+pub fn canvas(aspect: &str) -> Result<Canvas, fmt::Error> {
+    let pair = |separator: char| {
+        let (width, height) = aspect.split_once(separator)?;
+        let width = width.trim().parse::<u32>().ok().filter(|n| *n > 0)?;
+        let height = height.trim().parse::<u32>().ok().filter(|n| *n > 0)?;
+        Some((width, height))
+    };
+    match (pair(':'), pair('x')) {
+        (Some((width, height)), _) => Ok(Canvas {
+            width: CANVAS_WIDTH,
+            height: (CANVAS_WIDTH as f64 * height as f64 / width as f64).round() as u32,
+        }),
+        (None, Some((width, height))) => Ok(Canvas { width, height }),
+        (None, None) => {
+            eprintln!(
+                "The aspect \"{}\" is neither a ratio like 16:9 nor a size like 1024x768.",
+                aspect
+            );
+            Err(fmt::Error)
+        }
+    }
+}
+
+/// How far the runtime shrinks the texts and tables that overflow
+/// their part of the slide, as scales of their size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fit {
+    /// Never shrunk past it, clipped instead.
+    pub min: f64,
+    /// The compiler warns about the slides it guesses shrunk past it.
+    pub warn: f64,
+}
+
+impl Default for Fit {
+    fn default() -> Self {
+        Fit {
+            min: 0.5,
+            warn: 0.7,
+        }
+    }
+}
+
+/// Read a `.fit min [warn]`, both percentages like `60%`, or `.fit off`
+/// to leave the texts at their size.
+pub fn fit(raw_element: Vec<String>) -> Result<Option<Fit>, fmt::Error> {
+    is_tag_ok(&raw_element, TAG_FIT)?;
+    let scale = |arg: &str| match arg.trim_end_matches('%').parse::<f64>() {
+        Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(percent / 100.0),
+        _ => Err(invalid_argument(arg, &raw_element)),
+    };
+    match raw_element.tag_args()[..] {
+        ["off"] => Ok(None),
+        [min] => Ok(Some(Fit {
+            min: scale(min)?,
+            ..Fit::default()
+        })),
+        [min, warn] => Ok(Some(Fit {
+            min: scale(min)?,
+            warn: scale(warn)?,
+        })),
+        _ => {
+            eprintln!(
+                "The \"{}\" takes the smallest size, and maybe the one to warn at.",
+                raw_element[0]
+            );
+            Err(fmt::Error)
+        }
+    }
+}
+
+/// Information about the presentation itself, filling the `<head>`,
+/// the exports and the `{title}`, `{author}` and `{date}` placeholders
+/// of the footer, next to `{section}`.
+#[derive(Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub date: Option<String>,
+    /// Language code, like `en` or `pt-BR`.
+    pub lang: Option<String>,
+    pub subtitle: Option<String>,
+    /// Where the presentation is given.
+    pub event: Option<String>,
+}
+
+/// The value of a one line directive, like `.title My talk`. It can
+/// also be passed on the next line.
+pub fn directive(raw_element: Vec<String>, reference: &str) -> Result<String, fmt::Error> {
+    let args = raw_element.tag_args();
+    is_tag_ok(&raw_element, reference)?;
+    if !args.is_empty() {
+        Ok(args.join(" "))
+    } else {
+        is_element_ok(&raw_element, reference)?;
+        Ok(raw_element[1].clone())
+    }
+}
+
+/// The whole presentation as parsed from the input, before any
+/// rendering or exporting.
+pub struct Deck {
+    pub slides: Vec<Slide>,
+    pub mermaid: bool,
+    pub numbering: Option<Numbering>,
+    pub meta: Metadata,
+    /// Prepend a `SlideKind::Title` slide, asked by `.titleslide`.
+    pub title_slide: bool,
+    /// Heading of the `SlideKind::Agenda` slide asked by `.agenda`.
+    pub agenda: Option<String>,
+    /// Name of a built-in theme, or path to a `CSS` file.
+    pub theme: Option<String>,
+    /// Paths of the `.css` files, in order.
+    pub css: Vec<PathBuf>,
+    /// Paths of the `.js` files, in order.
+    pub js: Vec<PathBuf>,
+    pub fonts: Vec<Font>,
+    pub canvas: Canvas,
+    /// The `.order` of the header, slides can have their own.
+    pub order: Vec<OrderRule>,
+    /// Checked on every slide, from `.rule` and `.rules`.
+    pub rules: Vec<Rule>,
+    /// None after `.fit off`.
+    pub fit: Option<Fit>,
+}
+
+/// Translate the input lines into a `Deck`, applying the `SxPres`
+/// philosophy on the way.
+pub fn parse(input: Vec<String>, assets: &Assets) -> Result<Deck, fmt::Error> {
+    // A primitive form of slides, that will be translate
+    // into structured `Element` and `Slide` data.
+    let raw_slides: Vec<Vec<String>> = input
+        .into_iter()
+        .filter(|line| !line.is_empty() && !line.is_comment())
+        .collect::<Vec<String>>()
+        .split(|raw_slide| raw_slide.starts_with(SEPARATOR))
+        .map(|slide| slide.to_vec())
+        .collect::<Vec<Vec<String>>>();
+
+    let mut deck = Deck {
+        slides: vec![],
+        mermaid: false,
+        numbering: None,
+        meta: Metadata::default(),
+        title_slide: false,
+        agenda: None,
+        theme: None,
+        css: vec![],
+        js: vec![],
+        fonts: vec![],
+        canvas: Canvas::default(),
+        order: DEFAULT_ORDER.to_vec(),
+        rules: rules::DEFAULT_RULES.to_vec(),
+        fit: Some(Fit::default()),
+    };
+    // Footer, logo and section hold from their definition onwards.
+    let mut current_footer: Option<String> = None;
+    let mut current_logo: Option<Logo> = None;
+    let mut current_section: Option<String> = None;
+
+    for (slide_no, raw_slide) in raw_slides.into_iter().enumerate() {
+        let mut elements: Vec<Element> = vec![];
+        let mut is_draft = false;
+        let mut slide_notes: Vec<String> = vec![];
+        let mut background: Option<Element> = None;
+        let (mut no_footer, mut no_logo, mut no_number) = (false, false, false);
+        let mut new_section: Option<String> = None;
+        let mut slide_layout: Option<Layout> = None;
+        let mut slide_id: Option<String> = None;
+        let mut slide_order: Option<Vec<OrderRule>> = None;
+        // A first block made of deck directives only is a header.
+        let header = slide_no == 0
+            && !raw_slide.is_empty()
+            && raw_slide
+                .clone()
+                .split_on_tag()
+                .into_iter()
+                .all(|raw_element| DECK_TAGS.contains(&raw_element.clean_tag().tag()));
+
+        for mut raw_element in raw_slide.split_on_tag() {
+            // cleaning spaces on the tag line before processing
+            // is necessary, since the match bellow acts like a Turing
+            // machine over raw_element.
+            raw_element = raw_element.clean_tag();
+            let raw_result: Option<Element> = match raw_element.tag() {
+                TAG_HEADING => Some(heading(raw_element)?),
+                TAG_SUBHEADING => Some(subheading(raw_element)?),
+                TAG_ULIST => Some(ulist(raw_element)?),
+                TAG_ORDLIST => Some(ordlist(raw_element)?),
+                TAG_TEXT => Some(text(raw_element)?),
+                TAG_VIDEO => Some(video(raw_element, assets)?),
+                TAG_AUDIO if raw_element.tag_args().contains(&"background") => {
+                    background = Some(audio(raw_element, assets)?);
+                    None
+                }
+                TAG_AUDIO => Some(audio(raw_element, assets)?),
+                TAG_IMAGE => Some(image(raw_element, assets)?),
+                TAG_TABLE => Some(table(raw_element)?),
+                TAG_MERMAID => {
+                    deck.mermaid = true;
+                    Some(mermaid(raw_element)?)
+                }
+                TAG_MERMAIDSCRIPT => None,
+                TAG_FOOTER => {
+                    current_footer = Some(footer(raw_element)?);
+                    None
+                }
+                TAG_LOGO => {
+                    current_logo = Some(logo(raw_element, assets)?);
+                    None
+                }
+                TAG_NOFOOTER => {
+                    no_footer = true;
+                    None
+                }
+                TAG_NOLOGO => {
+                    no_logo = true;
+                    None
+                }
+                TAG_NUMBERING => {
+                    deck.numbering = Some(numbering(raw_element)?);
+                    None
+                }
+                TAG_NONUMBER => {
+                    no_number = true;
+                    None
+                }
+                TAG_TITLE => {
+                    deck.meta.title = Some(directive(raw_element, TAG_TITLE)?);
+                    None
+                }
+                TAG_AUTHOR => {
+                    deck.meta.author = Some(directive(raw_element, TAG_AUTHOR)?);
+                    None
+                }
+                TAG_DATE => {
+                    deck.meta.date = Some(directive(raw_element, TAG_DATE)?);
+                    None
+                }
+                TAG_LANG => {
+                    deck.meta.lang = Some(directive(raw_element, TAG_LANG)?);
+                    None
+                }
+                TAG_SUBTITLE => {
+                    deck.meta.subtitle = Some(directive(raw_element, TAG_SUBTITLE)?);
+                    None
+                }
+                TAG_EVENT => {
+                    deck.meta.event = Some(directive(raw_element, TAG_EVENT)?);
+                    None
+                }
+                TAG_TITLESLIDE => {
+                    deck.title_slide = true;
+                    None
+                }
+                TAG_THEME => {
+                    deck.theme = Some(directive(raw_element, TAG_THEME)?);
+                    None
+                }
+                TAG_ASPECT => {
+                    deck.canvas = canvas(&directive(raw_element, TAG_ASPECT)?)?;
+                    None
+                }
+                TAG_FONT => {
+                    deck.fonts.push(font(raw_element, assets)?);
+                    None
+                }
+                TAG_CSS => {
+                    deck.css
+                        .push(PathBuf::from(directive(raw_element, TAG_CSS)?));
+                    None
+                }
+                TAG_JS => {
+                    deck.js.push(PathBuf::from(directive(raw_element, TAG_JS)?));
+                    None
+                }
+                TAG_SECTION => {
+                    new_section = Some(directive(raw_element, TAG_SECTION)?);
+                    None
+                }
+                TAG_AGENDA => {
+                    let args = raw_element.tag_args();
+                    deck.agenda = Some(match args.is_empty() {
+                        true => String::from("Agenda"),
+                        false => args.join(" "),
+                    });
+                    None
+                }
+                // In the header, for every slide.
+                TAG_ORDER if header => {
+                    deck.order = order(raw_element)?;
+                    None
+                }
+                TAG_ORDER => {
+                    slide_order = Some(order(raw_element)?);
+                    None
+                }
+                // The limits hold for every slide, so they are set once.
+                TAG_RULE | TAG_RULES | TAG_OVERFLOW if !header => {
+                    eprintln!(
+                        "The \"{}\" belongs to the header, the first block, since it holds for every slide.",
+                        raw_element[0]
+                    );
+                    return Err(fmt::Error);
+                }
+                TAG_RULE => {
+                    rules::set(&mut deck.rules, rule(raw_element)?);
+                    None
+                }
+                TAG_RULES => {
+                    for rule in rules_file(&directive(raw_element, TAG_RULES)?)? {
+                        rules::set(&mut deck.rules, rule);
+                    }
+                    None
+                }
+                TAG_FIT => {
+                    deck.fit = fit(raw_element)?;
+                    None
+                }
+                TAG_OVERFLOW => {
+                    for rule in overflow(raw_element, &deck.rules)? {
+                        rules::set(&mut deck.rules, rule);
+                    }
+                    None
+                }
+                TAG_ID => {
+                    slide_id = Some(id(raw_element)?);
+                    None
+                }
+                TAG_LAYOUT => {
+                    slide_layout = Some(layout(raw_element)?);
+                    None
+                }
+                TAG_DRAFT => {
+                    is_draft = true;
+                    None
+                }
+                TAG_NOTES => {
+                    slide_notes.extend(notes(raw_element)?);
+                    None
+                }
+                _ => panic!("Unrecognised tag \"{}\".", &raw_element[0]),
+            };
+
+            if let Some(result) = raw_result {
+                elements.push(result);
+            }
+        }
+        if header {
+            continue;
+        }
+        // The `elements: Vec<Element>` suffer ordering, then the
+        // checking of the `deck.rules` in `apply_rules()`, e.g. the
+        // fifth element onwards left out, see StultusVisio philosophy.
+        // In another words, the main characteristic of SxPres is to
+        // free the user from formatting.
+        elements = elements.organize(slide_order.as_deref().unwrap_or(&deck.order));
+        let mut slide = Slide {
+            kind: SlideKind::Content,
+            number: None,
+            numbered: !no_number,
+            label: None,
+            draft: is_draft,
+            content: Ok(elements),
+            notes: slide_notes,
+            background,
+            footer: current_footer.clone().filter(|_| !no_footer),
+            logo: current_logo.clone().filter(|_| !no_logo),
+            section: None,
+            layout: slide_layout,
+            id: slide_id,
+        };
+        if let Some(name) = new_section {
+            // A `.section` alone on its slide makes it the divider,
+            // otherwise one is put before the slide.
+            let divider = vec![heading(vec![
+                format!("{}{}", TAG_MARKER, TAG_HEADING),
+                name.clone(),
+            ])?];
+            current_section = Some(name);
+            match &slide.content {
+                Ok(elements) if elements.is_empty() && slide.background.is_none() => {
+                    slide.kind = SlideKind::Section;
+                    slide.content = Ok(divider);
+                }
+                _ => deck.slides.push(Slide {
+                    footer: slide.footer.clone(),
+                    logo: slide.logo.clone(),
+                    section: current_section.clone(),
+                    draft: slide.draft,
+                    ..Slide::generated(SlideKind::Section, divider)
+                }),
+            }
+        }
+        slide.section = current_section.clone();
+        deck.slides.push(slide);
+    }
+
+    if deck.title_slide {
+        let logo = deck.slides.first().and_then(|slide| slide.logo.as_ref());
+        let slide = title_slide(&deck.meta, logo, assets)?;
+        deck.slides.insert(0, slide);
+    }
+    if deck.agenda.is_some() {
+        let at = usize::from(deck.title_slide);
+        deck.slides
+            .insert(at, Slide::generated(SlideKind::Agenda, vec![]));
+    }
+    // A header alone still makes a presentation, of an empty slide.
+    if deck.slides.is_empty() {
+        deck.slides
+            .push(Slide::generated(SlideKind::Content, vec![]));
+    }
+    apply_rules(&mut deck)?;
+    number(&mut deck);
+    if let Some(title) = &deck.agenda {
+        let agenda = agenda(title, &deck.slides)?;
+        if let Some(slide) = deck
+            .slides
+            .iter_mut()
+            .find(|slide| slide.kind == SlideKind::Agenda)
+        {
+            slide.content = Ok(agenda);
+        }
+    }
+    fill_footers(&mut deck);
+    resolve_references(&mut deck)?;
+    Ok(deck)
+}
+
+/// Check the content slides against `deck.rules`, once all the slides
+/// are in place, so the messages give the numbers shown. What
+/// `rules::Action::Split` moves out goes to continuation slides, right
+/// after, numbered as the slides that follow.
+fn apply_rules(deck: &mut Deck) -> Result<(), fmt::Error> {
+    let mut slides = Vec::with_capacity(deck.slides.len());
+    let mut number = 0;
+    for mut slide in std::mem::take(&mut deck.slides) {
+        let at = match slide.draft {
+            true => "draft".to_string(),
+            false => (number + 1).to_string(),
+        };
+        let mut pages = match (&mut slide.content, slide.kind) {
+            (Ok(elements), SlideKind::Content) => {
+                rules::apply(&deck.rules, std::mem::take(elements), &at)?
+            }
+            _ => vec![],
+        }
+        .into_iter();
+        if let Some(elements) = pages.next() {
+            slide.content = Ok(elements);
+        }
+        let continuations: Vec<Slide> = pages
+            .map(|elements| Slide {
+                numbered: slide.numbered,
+                draft: slide.draft,
+                footer: slide.footer.clone(),
+                logo: slide.logo.clone(),
+                section: slide.section.clone(),
+                layout: slide.layout,
+                ..Slide::generated(SlideKind::Content, elements)
+            })
+            .collect();
+        for slide in std::iter::once(slide).chain(continuations) {
+            if !slide.draft {
+                number += 1;
+            }
+            slides.push(slide);
+        }
+    }
+    deck.slides = slides;
+    Ok(())
+}
+
+/// The elements of the `.agenda` slide: its heading and the sections,
+/// with the number of their dividers.
+fn agenda(title: &str, slides: &[Slide]) -> Result<Vec<Element>, fmt::Error> {
+    let tag = |reference: &str| format!("{}{}", TAG_MARKER, reference);
+    let mut items = vec![tag(TAG_ORDLIST)];
+    for slide in slides {
+        if let (SlideKind::Section, Some(number), Some(section)) =
+            (slide.kind, slide.number, &slide.section)
+        {
+            items.push(format!("{} — {}", section, number));
+        }
+    }
+    let mut elements = vec![heading(vec![tag(TAG_HEADING), title.to_string()])?];
+    match items.len() {
+        1 => eprintln!("The .{} has no .{} to list.", TAG_AGENDA, TAG_SECTION),
+        _ => elements.push(ordlist(items)?),
+    }
+    Ok(elements)
+}
+
+/// The slide `.titleslide` asks for: the logo of the first slide, the
+/// title, the subtitle and a text with the author, date and event.
+fn title_slide(meta: &Metadata, logo: Option<&Logo>, assets: &Assets) -> Result<Slide, fmt::Error> {
+    let title = match &meta.title {
+        Some(title) => title,
+        None => {
+            eprintln!("A .{} needs a .{} to be built.", TAG_TITLESLIDE, TAG_TITLE);
+            return Err(fmt::Error);
+        }
+    };
+    let tag = |reference: &str| format!("{}{}", TAG_MARKER, reference);
+    let mut elements = vec![];
+    if let Some(logo) = logo {
+        elements.push(image(vec![tag(TAG_IMAGE), logo.path.clone()], assets)?);
+    }
+    elements.push(heading(vec![tag(TAG_HEADING), title.clone()])?);
+    if let Some(subtitle) = &meta.subtitle {
+        elements.push(subheading(vec![tag(TAG_SUBHEADING), subtitle.clone()])?);
+    }
+    let byline: Vec<String> = [&meta.author, &meta.date, &meta.event]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if !byline.is_empty() {
+        elements.push(text([vec![tag(TAG_TEXT)], byline].concat())?);
+    }
+    Ok(Slide::generated(SlideKind::Title, elements))
+}
+
+/// Whether a cross-reference points to an `.id`, or to a `/14` or
+/// `/id` address of the runtime, rather than to anything else.
+fn is_target(target: &str) -> bool {
+    !target.is_empty()
+        && target
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+}
+
+/// Replace each `@ref(id)` and `[text](#id)` of `text` by what
+/// `replace` makes of the optional text and the id.
+fn cross_references(
+    text: &str,
+    mut replace: impl FnMut(Option<&str>, &str) -> Result<String, fmt::Error>,
+) -> Result<String, fmt::Error> {
+    let mut out = String::new();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        let found = if let Some(after) = rest.strip_prefix("@ref(") {
+            after
+                .find(')')
+                .filter(|end| is_target(&after[..*end]))
+                .map(|end| (None, &after[..end], "@ref(".len() + end + 1))
+        } else if let Some(after) = rest.strip_prefix('[') {
+            after.find("](#").and_then(|middle| {
+                let label = &after[..middle];
+                let tail = &after[middle + "](#".len()..];
+                tail.find(')')
+                    .filter(|end| !label.contains(['[', ']', '<']) && is_target(&tail[..*end]))
+                    .map(|end| {
+                        (
+                            Some(label),
+                            &tail[..end],
+                            1 + middle + "](#".len() + end + 1,
+                        )
+                    })
+            })
+        } else {
+            None
+        };
+        match found {
+            Some((label, id, len)) => {
+                out += &replace(label, id)?;
+                i += len;
+            }
+            None => {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Point the cross-references of the slides to the number of the
+/// slide with the `.id`, or of the `/14` or `/id` address: a link to
+/// jump to it on the slide, the number in the exports. One to no
+/// slide, or to a draft, is an error.
+fn resolve_references(deck: &mut Deck) -> Result<(), fmt::Error> {
+    let numbers: Vec<(String, Option<usize>)> = deck
+        .slides
+        .iter()
+        .filter_map(|slide| slide.id.clone().map(|id| (id, slide.number)))
+        .collect();
+    let total = deck.slides.iter().filter(|slide| !slide.draft).count();
+    for (i, (id, number)) in numbers.iter().enumerate() {
+        if numbers[..i].iter().any(|(other, _)| other == id) {
+            eprintln!(
+                "The .{} \"{}\" of the slide no. {} is taken by another slide.",
+                TAG_ID,
+                id,
+                number.map_or("draft".to_string(), |number| number.to_string())
+            );
+            return Err(fmt::Error);
+        }
+    }
+    for slide in deck.slides.iter_mut() {
+        let at = slide
+            .number
+            .map_or("draft".to_string(), |number| number.to_string());
+        let number = |target: &str| {
+            let id = match target.strip_prefix('/') {
+                Some(address) => match address.parse::<usize>() {
+                    Ok(number) if (1..=total).contains(&number) => return Ok(number),
+                    Ok(number) => {
+                        eprintln!(
+                            "The slide no. {} refers to the slide no. {}, out of {}.",
+                            at, number, total
+                        );
+                        return Err(fmt::Error);
+                    }
+                    Err(_) => address,
+                },
+                None => target,
+            };
+            match numbers.iter().find(|(other, _)| other == id) {
+                Some((_, Some(number))) => Ok(*number),
+                Some((_, None)) => {
+                    eprintln!("The slide no. {} refers to \"{}\", a draft.", at, id);
+                    Err(fmt::Error)
+                }
+                None => {
+                    eprintln!(
+                        "The slide no. {} refers to \"{}\", but no .{} names it.",
+                        at, id, TAG_ID
+                    );
+                    Err(fmt::Error)
+                }
+            }
+        };
+        let link = |label: Option<&str>, target: &str| {
+            let number = number(target)?;
+            Ok(format!(
+                "<a class=\"ref\" href=\"#/{}\">{}</a>",
+                target.trim_start_matches('/'),
+                label.map_or(number.to_string(), |label| label.to_string())
+            ))
+        };
+        let plain = |label: Option<&str>, id: &str| {
+            let number = number(id)?;
+            Ok(match label {
+                Some(label) => format!("{} ({})", label, number),
+                None => number.to_string(),
+            })
+        };
+        if let Ok(elements) = slide.content.as_mut() {
+            for element in elements.iter_mut() {
+                element.content = cross_references(&element.content, link)?;
+                for line in element.source.iter_mut() {
+                    *line = cross_references(line, plain)?;
+                }
+            }
+        }
+        for line in slide.notes.iter_mut() {
+            *line = cross_references(line, plain)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the metadata placeholders of the footers, once the whole
+/// input is read.
+fn fill_footers(deck: &mut Deck) {
+    let meta = &deck.meta;
+    for (foot, section) in deck
+        .slides
+        .iter_mut()
+        .filter_map(|slide| slide.footer.as_mut().map(|foot| (foot, &slide.section)))
+    {
+        *foot = foot
+            .replace("{title}", meta.title.as_deref().unwrap_or_default())
+            .replace("{author}", meta.author.as_deref().unwrap_or_default())
+            .replace("{date}", meta.date.as_deref().unwrap_or_default())
+            .replace("{section}", section.as_deref().unwrap_or_default());
+    }
+}
+
+/// Count the slides of the `Deck` and give them the labels asked by
+/// its `Numbering`.
+fn number(deck: &mut Deck) {
+    let total = deck.slides.iter().filter(|slide| !slide.draft).count();
+    let mut number = 0;
+    for slide in deck.slides.iter_mut() {
+        if !slide.draft {
+            number += 1;
+            slide.number = Some(number);
+        }
+        slide.label = match (&deck.numbering, slide.number) {
+            (Some(numbering), Some(number)) if slide.numbered => {
+                if numbering.skip_title && slide.kind == SlideKind::Title {
+                    None
+                } else if numbering.total {
+                    Some(format!("{}/{}", number, total))
+                } else {
+                    Some(number.to_string())
+                }
+            }
+            _ => None,
+        };
+    }
+}
+
+/// The themes embedded into `SxPres`. They only set the `CSS` variables
+/// declared on top of `style.css`, as custom themes should.
+pub const THEMES: [(&str, &str); 4] = [
+    ("light", include_str!("./themes/light.css")),
+    ("dark", include_str!("./themes/dark.css")),
+    ("high-contrast", include_str!("./themes/high-contrast.css")),
+    ("corporate", include_str!("./themes/corporate.css")),
+];
+
+/// The `CSS` of a theme, built-in or read from a file.
+pub fn theme(theme: &str) -> Result<String, fmt::Error> {
+    match THEMES.iter().find(|(name, _)| *name == theme) {
+        Some((_, css)) => Ok(css.to_string()),
+        None => fs::read_to_string(theme).map_err(|err| {
+            eprintln!(
+                "The theme \"{}\" is not built-in nor a file: {}",
+                theme, err
+            );
+            fmt::Error
+        }),
+    }
+}
+
+/// A font embedded into the presentation by `.font`.
+pub struct Font {
+    /// Path as written in the input.
+    pub path: String,
+    pub family: String,
+    /// The embedded data, or the external asset.
+    pub src: String,
+}
+
+/// Read a `.font path.woff2 [family]`, the family defaulting to the
+/// file name.
+pub fn font(raw_element: Vec<String>, assets: &Assets) -> Result<Font, fmt::Error> {
+    let value = directive(raw_element, TAG_FONT)?;
+    let (path, family) = match value.split_once(' ') {
+        Some((path, family)) => (path.to_string(), family.to_string()),
+        None => {
+            let family = Path::new(&value)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            (value, family)
+        }
+    };
+    Ok(Font {
+        src: media(path.clone(), "font", assets)?,
+        path,
+        family,
+    })
+}
+
+/// The `@font-face` of the fonts, put ahead of the default ones, so
+/// the first font is the one of the text and the next ones only fill
+/// its missing glyphs.
+fn fonts_css(fonts: &[Font]) -> String {
+    if fonts.is_empty() {
+        return String::new();
+    }
+    let mut css = String::new();
+    for font in fonts {
+        css += &format!(
+            "@font-face {{ font-family: \"{}\"; src: url(\"{}\"); }}",
+            font.family, font.src
+        );
+    }
+    // Ahead of the `--font-family` of the theme, see `style.css`, the
+    // trailing comma joining the two lists.
+    let families: Vec<String> = fonts
+        .iter()
+        .map(|font| format!("\"{}\",", font.family))
+        .collect();
+    css + &format!(":root {{ --custom-font: {}; }}", families.join(" "))
+}
+
+/// Read a `.css` or `.js` file to be inlined.
+fn custom_file(path: &Path) -> Result<String, fmt::Error> {
+    fs::read_to_string(path).map_err(|err| {
+        eprintln!("Can't read the custom file {}: {}", path.display(), err);
+        fmt::Error
+    })
+}
+
+/// Finally condense back a `Deck` into `HTML` that can be
 /// printed or outputed.
-pub fn render(
-    logo: Result<String, fmt::Error>,
-    footer: Result<String, fmt::Error>,
-    mermaid: bool,
-    slides: Vec<Slide>,
-) -> Result<HTML, fmt::Error> {
+pub fn render(deck: Deck) -> Result<HTML, fmt::Error> {
     let mut body: String = String::from("<body>");
-    if slides.len() < 1 {
-        panic!("Zero slides built.");
+    if deck.slides.is_empty() {
+        eprintln!("Zero slides built.");
+        return Err(fmt::Error);
     } else {
-        for slide in slides {
+        for slide in &deck.slides {
             body = body + &format!("{}", slide);
         }
     };
 
-    body = body + "</body>";
+    body += "</body>";
+
+    // The runtime measures and shrinks the texts, but can't tell the
+    // author, so a guess of it is made here.
+    if let Some(fit) = deck.fit {
+        for slide in deck
+            .slides
+            .iter()
+            .filter(|slide| slide.kind == SlideKind::Content)
+        {
+            let elements = match &slide.content {
+                Ok(elements) => elements,
+                Err(_) => continue,
+            };
+            let scale = layout::estimated_scale(slide.layout, elements, deck.canvas);
+            if scale < fit.warn {
+                eprintln!(
+                    "The slide no. {} may need its text shrunk to {:.0}% to fit{}.",
+                    slide.number.map_or("draft".to_string(), |n| n.to_string()),
+                    scale * 100.0,
+                    match scale < fit.min {
+                        true => format!(
+                            ", past the {:.0}% of .fit, so it is clipped",
+                            fit.min * 100.0
+                        ),
+                        false => String::new(),
+                    }
+                );
+            }
+        }
+    }
 
     let mut mermaid_script = String::new();
     // TODO: Wrap mermaid_file in Option<String>.
@@ -587,51 +2153,129 @@ pub fn render(
     // the script to a page is to import, because the script calls for
     // other ones in the mermaid remote server, thus turning the job too
     // error prone.
-    if mermaid {
+    if deck.mermaid {
         mermaid_script = "<script type=\"module\">import mermaid from 'https://cdn.jsdelivr.net/npm/mermaid@11/dist/mermaid.esm.min.mjs';mermaid.initialize({ startOnLoad: true });</script>".to_string();
     };
 
-    let foot = match footer {
-        Ok(foot) => foot,
-        _ => "".to_string(),
-    };
-
-    let logo_img = match logo {
-        Ok(logo) => logo,
-        _ => "".to_string(),
+    let mut head = String::from("<meta charset=\"utf-8\">");
+    if let Some(title) = &deck.meta.title {
+        head += &format!("<title>{}</title>", attribute(title));
+    }
+    if let Some(author) = &deck.meta.author {
+        head += &format!("<meta name=\"author\" content=\"{}\">", attribute(author));
+    }
+    if let Some(date) = &deck.meta.date {
+        head += &format!("<meta name=\"date\" content=\"{}\">", attribute(date));
+    }
+    let lang = match &deck.meta.lang {
+        Some(lang) => format!(" lang=\"{}\"", attribute(lang)),
+        None => String::new(),
     };
 
-    let script = "<script>".to_owned()
-        + &String::from_utf8(include_bytes!("./script.js").to_vec())
+    let mut script = "<script>".to_owned()
+        + std::str::from_utf8(include_bytes!("./script.js"))
             .expect("Can't include \'script.js\' during compilation.")
         + "</script>";
+    // Each custom script on its own, so an error doesn't stop the others.
+    for path in &deck.js {
+        script += &format!("<script>{}</script>", custom_file(path)?);
+    }
 
-    let css = "<style>".to_owned()
-        + &String::from_utf8(include_bytes!("./style.css").to_vec())
-            .expect("Can't include \'style.css\' during compilation.")
-        + "</style>";
+    let mut css = "<style>".to_owned()
+        + std::str::from_utf8(include_bytes!("./style.css"))
+            .expect("Can't include \'style.css\' during compilation.");
+    let Canvas { width, height } = deck.canvas;
+    css += &format!(
+        ":root {{ --slide-width: {}px; --slide-height: {}px; --fit-min: {}; }} @page {{ size: {}px {}px; margin: 0; }}",
+        width,
+        height,
+        deck.fit.map_or(1.0, |fit| fit.min),
+        width,
+        height
+    );
+    if let Some(name) = &deck.theme {
+        css += &theme(name)?;
+    }
+    css += &layout::css();
+    css += &fonts_css(&deck.fonts);
+    for path in &deck.css {
+        css += &custom_file(path)?;
+    }
+    css += "</style>";
 
-    Ok(HTML(String::from(format!(
+    Ok(HTML(format!(
         "<!DOCTYPE html>\n
-                    <html>\n
+                    <html{}>\n
                     <head>\n
-                    {}{}{}{}
+                    {}{}{}
                     <div id=\"marcador\"></div>
                     <div id=\"popup\">
                         <p><span id=\"conteudo-popup\"></span></p>
                     </div></head>\n
                     {}{}
                     </html>",
+        &lang,
+        &head,
         &mermaid_script,
         &css,
-        &foot,
-        &logo_img,
         &body,
         // `&script` has to be inserted at the end, so that only with
         // the whole page built it can calls to document.ElementById's
         // methods in the /src/script.js.
         &script
-    ))))
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `cross_references()` found, as `label->target`.
+    fn references(text: &str) -> String {
+        cross_references(text, |label, target| {
+            Ok(format!("{{{}->{}}}", label.unwrap_or_default(), target))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn cross_reference_forms() {
+        assert_eq!(
+            references("See [results](#results), or @ref(results)."),
+            "See {results->results}, or {->results}."
+        );
+        assert_eq!(references("[slide 2](#/2)"), "{slide 2->/2}");
+        assert_eq!(references("@ref(/intro)"), "{->/intro}");
+    }
+
+    #[test]
+    fn cross_references_left_untouched() {
+        for text in [
+            "@ref(results",
+            "@ref() and @ref(two words)",
+            "[a](#b",
+            "[a](#)",
+            "[a](results)",
+            "[<em>a</em>](#b)",
+            "[a](#b c)",
+            "[",
+            "@ref(",
+        ] {
+            assert_eq!(references(text), text);
+        }
+        // The label goes from the last `[`.
+        assert_eq!(references("[a [b](#c)"), "[a {b->c}");
+        assert_eq!(references("[a] [b](#c)"), "[a] {b->c}");
+    }
+
+    #[test]
+    fn cross_references_after_multibyte_text() {
+        assert_eq!(
+            references("Ação → @ref(fim), [é](#fim) ✓"),
+            "Ação → {->fim}, {é->fim} ✓"
+        );
+        assert_eq!(references("ç[ã](#x"), "ç[ã](#x");
+    }
 }
 
 //    This file is part of StultusVisio.
@@ -0,0 +1,551 @@
+// See licence at the end.
+
+// By Jefferson T.
+// https://jeffersontorres.com.br
+
+//! The limits of the `SxPres` philosophy, checked on each slide once
+//! its elements are ordered. A `Rule` pairs a `Check` and its limit
+//! with what to do with the slides going over it.
+
+use {
+    crate::{
+        heading, ordlist_from, subheading, text, ulist, Element, ElementNature, TAG_HEADING,
+        TAG_MARKER, TAG_ORDLIST, TAG_SUBHEADING, TAG_TEXT, TAG_ULIST,
+    },
+    std::fmt,
+};
+
+/// What a `Rule` counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// Elements of the slide.
+    MaxElements,
+    MaxVideos,
+    MaxTables,
+    /// Words of each text.
+    MaxWords,
+    /// Items of each list, ordered or not.
+    MaxListItems,
+}
+
+impl Check {
+    pub const ALL: [Check; 5] = [
+        Check::MaxElements,
+        Check::MaxVideos,
+        Check::MaxTables,
+        Check::MaxWords,
+        Check::MaxListItems,
+    ];
+
+    /// Name given to `.rule`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MaxElements => "max-elements",
+            Self::MaxVideos => "max-videos",
+            Self::MaxTables => "max-tables",
+            Self::MaxWords => "max-words",
+            Self::MaxListItems => "max-list-items",
+        }
+    }
+
+    /// What is counted, for the messages.
+    fn counted(&self) -> &'static str {
+        match self {
+            Self::MaxElements => "elements",
+            Self::MaxVideos => "videos",
+            Self::MaxTables => "tables",
+            Self::MaxWords => "words in a text",
+            Self::MaxListItems => "items in a list",
+        }
+    }
+}
+
+/// What a `Rule` does with a slide going over its limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Tells about it and goes on.
+    Warn,
+    /// Stops the compilation.
+    Error,
+    /// Leaves out what is over the limit.
+    Drop,
+    /// Moves what is over the limit to a continuation slide, right
+    /// after, under the same heading marked `(cont.)`.
+    Split,
+    /// Doesn't check, like `.rule max-elements off`.
+    Off,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Warn,
+        Action::Error,
+        Action::Drop,
+        Action::Split,
+        Action::Off,
+    ];
+
+    /// Name given to `.rule`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Error => "error",
+            Self::Drop => "drop",
+            Self::Split => "split",
+            Self::Off => "off",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub check: Check,
+    pub limit: usize,
+    pub action: Action,
+}
+
+/// The rules when none is given: the four elements of the `SxPres`
+/// philosophy, the fifth onwards being left out.
+pub const DEFAULT_RULES: [Rule; 1] = [Rule {
+    check: Check::MaxElements,
+    limit: 4,
+    action: Action::Drop,
+}];
+
+/// Add a rule, taking the place of the one with the same `Check`.
+pub fn set(rules: &mut Vec<Rule>, rule: Rule) {
+    rules.retain(|other| other.check != rule.check);
+    rules.push(rule);
+}
+
+/// The elements over the limit of a counting `Check`, found from the
+/// end of the slide.
+fn over(check: Check, limit: usize, elements: &[Element]) -> Vec<usize> {
+    let counted = |nature: &ElementNature| match check {
+        Check::MaxVideos => *nature == ElementNature::Video,
+        Check::MaxTables => *nature == ElementNature::Table,
+        _ => true,
+    };
+    let mut found: Vec<usize> = (0..elements.len())
+        .filter(|i| counted(&elements[*i].nature))
+        .collect();
+    found.drain(..found.len().min(limit));
+    found
+}
+
+/// The text split after its first `limit` words, the lines kept apart.
+fn split_words(lines: &[String], limit: usize) -> (Vec<String>, Vec<String>) {
+    let (mut kept, mut rest) = (vec![], vec![]);
+    let mut words = 0;
+    for line in lines {
+        let line_words: Vec<&str> = line.split_whitespace().collect();
+        let room = limit.saturating_sub(words).min(line_words.len());
+        words += line_words.len();
+        if room > 0 {
+            kept.push(line_words[..room].join(" "));
+        }
+        if room < line_words.len() {
+            rest.push(line_words[room..].join(" "));
+        }
+    }
+    (kept, rest)
+}
+
+/// Marks the heading of a continuation slide.
+pub const CONTINUED: &str = " (cont.)";
+
+fn tag(reference: &str) -> Vec<String> {
+    vec![format!("{}{}", TAG_MARKER, reference)]
+}
+
+/// A text or list built again from some of its lines, an ordered list
+/// numbered from `first`.
+fn rebuild(
+    nature: &ElementNature,
+    lines: Vec<String>,
+    first: usize,
+) -> Result<Element, fmt::Error> {
+    match nature {
+        ElementNature::Text => text([tag(TAG_TEXT), lines].concat()),
+        ElementNature::OrdList => ordlist_from([tag(TAG_ORDLIST), lines].concat(), first),
+        _ => ulist([tag(TAG_ULIST), lines].concat()),
+    }
+}
+
+/// The heading, or else the subheading, of a slide marked for its
+/// continuation.
+fn continued(elements: &[Element]) -> Result<Option<Element>, fmt::Error> {
+    let element = match elements
+        .iter()
+        .find(|element| element.nature.is_heading_or_subheading())
+    {
+        Some(element) => element,
+        None => return Ok(None),
+    };
+    let mut title = element.source[0].clone();
+    if !title.ends_with(CONTINUED) {
+        title += CONTINUED;
+    }
+    let reference = match element.nature {
+        ElementNature::Heading => TAG_HEADING,
+        _ => TAG_SUBHEADING,
+    };
+    let raw_element = [tag(reference), vec![title]].concat();
+    match element.nature {
+        ElementNature::Heading => heading(raw_element).map(Some),
+        _ => subheading(raw_element).map(Some),
+    }
+}
+
+/// Check the elements of the slide no. `at` against the rules,
+/// in turn. The slide comes first in the result, followed by those
+/// made by `Action::Split`, themselves checked again.
+pub fn apply(
+    rules: &[Rule],
+    mut elements: Vec<Element>,
+    at: &str,
+) -> Result<Vec<Vec<Element>>, fmt::Error> {
+    let mut moved: Vec<Element> = vec![];
+    for rule in rules.iter().filter(|rule| rule.action != Action::Off) {
+        let Rule {
+            check,
+            limit,
+            action,
+        } = *rule;
+        let report = |what: &str| -> Result<(), fmt::Error> {
+            eprintln!(
+                "The slide no. {} has more than {} {}: {} {}.",
+                at,
+                limit,
+                check.counted(),
+                what,
+                match action {
+                    Action::Warn => "left as it is",
+                    Action::Error => "stops the compilation",
+                    Action::Drop => "was discarded",
+                    _ => "goes to the next slide",
+                }
+            );
+            match action {
+                Action::Error => Err(fmt::Error),
+                _ => Ok(()),
+            }
+        };
+        match check {
+            Check::MaxElements | Check::MaxVideos | Check::MaxTables => {
+                let over = over(check, limit, &elements);
+                for i in &over {
+                    report(&format!("an element of nature {}", elements[*i].nature))?;
+                }
+                if matches!(action, Action::Drop | Action::Split) {
+                    for i in over.into_iter().rev() {
+                        let element = elements.remove(i);
+                        if action == Action::Split {
+                            moved.insert(0, element);
+                        }
+                    }
+                }
+            }
+            Check::MaxWords | Check::MaxListItems => {
+                for element in elements.iter_mut() {
+                    let (kept, rest) = match (check, &element.nature) {
+                        (Check::MaxWords, ElementNature::Text) => {
+                            split_words(&element.source, limit)
+                        }
+                        (Check::MaxListItems, ElementNature::List | ElementNature::OrdList) => {
+                            let at = element.source.len().min(limit);
+                            (element.source[..at].to_vec(), element.source[at..].to_vec())
+                        }
+                        _ => continue,
+                    };
+                    if rest.is_empty() {
+                        continue;
+                    }
+                    report(&format!("the rest of a {}", element.nature))?;
+                    let nature = element.nature.clone();
+                    let first = element.start.unwrap_or(1);
+                    let next = first + kept.len();
+                    match action {
+                        Action::Drop => *element = rebuild(&nature, kept, first)?,
+                        Action::Split => {
+                            *element = rebuild(&nature, kept, first)?;
+                            moved.push(rebuild(&nature, rest, next)?);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+    // A single element per slide leaves no room for the heading.
+    let room = !rules.iter().any(|rule| {
+        rule.check == Check::MaxElements && rule.action == Action::Split && rule.limit < 2
+    });
+    if !moved.is_empty() && room {
+        if let Some(heading) = continued(&elements)? {
+            moved.insert(0, heading);
+        }
+    }
+    let mut slides = vec![elements];
+    if !moved.is_empty() {
+        slides.extend(apply(rules, moved, at)?);
+    }
+    Ok(slides)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element, rule, CleanTag, TAG_RULE};
+    use ElementNature::*;
+
+    fn parsed(line: &str) -> Result<Rule, fmt::Error> {
+        rule(vec![format!("{}{} {}", TAG_MARKER, TAG_RULE, line)].clean_tag())
+    }
+
+    fn written(reference: &str, lines: &[&str]) -> Element {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        match reference {
+            TAG_HEADING => heading([tag(reference), lines].concat()),
+            TAG_SUBHEADING => subheading([tag(reference), lines].concat()),
+            TAG_TEXT => text([tag(reference), lines].concat()),
+            TAG_ORDLIST => ordlist_from([tag(reference), lines].concat(), 1),
+            _ => ulist([tag(reference), lines].concat()),
+        }
+        .unwrap()
+    }
+
+    fn limit(check: Check, limit: usize, action: Action) -> Vec<Rule> {
+        vec![Rule {
+            check,
+            limit,
+            action,
+        }]
+    }
+
+    fn natures(pages: &[Vec<Element>]) -> Vec<Vec<ElementNature>> {
+        pages
+            .iter()
+            .map(|page| page.iter().map(|element| element.nature.clone()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn rule_parsing() {
+        let rule = |check, limit, action| Rule {
+            check,
+            limit,
+            action,
+        };
+        assert_eq!(
+            parsed("max-elements 3 split").ok(),
+            Some(rule(Check::MaxElements, 3, Action::Split))
+        );
+        assert_eq!(
+            parsed("max-words 40").ok(),
+            Some(rule(Check::MaxWords, 40, Action::Warn))
+        );
+        assert_eq!(
+            parsed("max-videos off").ok(),
+            Some(rule(Check::MaxVideos, 0, Action::Off))
+        );
+        assert_eq!(
+            parsed("max-tables 0 error").ok(),
+            Some(rule(Check::MaxTables, 0, Action::Error))
+        );
+        assert_eq!(
+            parsed("max-elements 0 drop").ok(),
+            Some(rule(Check::MaxElements, 0, Action::Drop))
+        );
+        for line in [
+            "",
+            "max-slides 3",
+            "max-elements",
+            "max-elements three",
+            "max-elements 3 explode",
+            "max-elements 3 off",
+            "max-elements 3 split now",
+            // Would never end, or leave an empty text or list.
+            "max-elements 0 split",
+            "max-list-items 0 split",
+            "max-words 0 drop",
+            "max-list-items 0 drop",
+        ] {
+            assert!(parsed(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn set_replaces_the_same_check() {
+        let mut rules = DEFAULT_RULES.to_vec();
+        set(&mut rules, parsed("max-words 10").unwrap());
+        set(&mut rules, parsed("max-elements 6 split").unwrap());
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1], parsed("max-elements 6 split").unwrap());
+    }
+
+    #[test]
+    fn warn_off_and_error() {
+        let elements = || vec![element(Heading), element(Text), element(Image)];
+        for action in [Action::Warn, Action::Off] {
+            let pages = apply(&limit(Check::MaxElements, 2, action), elements(), "1").unwrap();
+            assert_eq!(natures(&pages), [[Heading, Text, Image]], "{:?}", action);
+        }
+        assert!(apply(
+            &limit(Check::MaxElements, 2, Action::Error),
+            elements(),
+            "1"
+        )
+        .is_err());
+        assert!(apply(
+            &limit(Check::MaxElements, 3, Action::Error),
+            elements(),
+            "1"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn drop_counted_elements() {
+        let cases = [
+            (
+                Check::MaxElements,
+                2,
+                vec![Heading, Text, Image, Text],
+                vec![Heading, Text],
+            ),
+            (
+                Check::MaxVideos,
+                1,
+                vec![Video, Text, Video],
+                vec![Video, Text],
+            ),
+            (Check::MaxTables, 0, vec![Table, Text, Table], vec![Text]),
+        ];
+        for (check, most, given, kept) in cases {
+            let elements = given.into_iter().map(element).collect();
+            let pages = apply(&limit(check, most, Action::Drop), elements, "1").unwrap();
+            assert_eq!(natures(&pages), [kept], "{:?}", check);
+        }
+    }
+
+    #[test]
+    fn drop_words_and_items() {
+        let words = written(TAG_TEXT, &["one two three four", "five"]);
+        let pages = apply(&limit(Check::MaxWords, 3, Action::Drop), vec![words], "1").unwrap();
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0][0].source, ["one two three"]);
+
+        for reference in [TAG_ULIST, TAG_ORDLIST] {
+            let list = written(reference, &["a", "b", "c"]);
+            let pages = apply(
+                &limit(Check::MaxListItems, 2, Action::Drop),
+                vec![list],
+                "1",
+            )
+            .unwrap();
+            assert_eq!(pages.len(), 1, "{}", reference);
+            assert_eq!(pages[0][0].source, ["a", "b"], "{}", reference);
+        }
+    }
+
+    #[test]
+    fn split_elements_under_the_heading() {
+        let elements = vec![
+            written(TAG_HEADING, &["Title"]),
+            element(Text),
+            element(Image),
+            element(Table),
+            element(Text),
+        ];
+        let pages = apply(&limit(Check::MaxElements, 2, Action::Split), elements, "1").unwrap();
+        assert_eq!(
+            natures(&pages),
+            [
+                [Heading, Text],
+                [Heading, Image],
+                [Heading, Table],
+                [Heading, Text]
+            ]
+        );
+        for page in &pages[1..] {
+            assert_eq!(page[0].source, ["Title (cont.)"]);
+        }
+    }
+
+    #[test]
+    fn split_videos() {
+        let elements = vec![
+            written(TAG_SUBHEADING, &["Clips"]),
+            element(Video),
+            element(Video),
+        ];
+        let pages = apply(&limit(Check::MaxVideos, 1, Action::Split), elements, "1").unwrap();
+        assert_eq!(natures(&pages), [[Subheading, Video], [Subheading, Video]]);
+    }
+
+    #[test]
+    fn split_words_and_items() {
+        let words = written(TAG_TEXT, &["one two three four five"]);
+        let pages = apply(&limit(Check::MaxWords, 2, Action::Split), vec![words], "1").unwrap();
+        let sources: Vec<_> = pages.iter().map(|page| page[0].source.clone()).collect();
+        assert_eq!(sources, [["one two"], ["three four"], ["five"]]);
+
+        let elements = vec![
+            written(TAG_HEADING, &["List"]),
+            written(TAG_ORDLIST, &["a", "b", "c", "d", "e"]),
+        ];
+        let pages = apply(&limit(Check::MaxListItems, 2, Action::Split), elements, "1").unwrap();
+        assert_eq!(
+            natures(&pages),
+            [[Heading, OrdList], [Heading, OrdList], [Heading, OrdList]]
+        );
+        let starts: Vec<_> = pages.iter().map(|page| page[1].start).collect();
+        assert_eq!(starts, [None, Some(3), Some(5)]);
+        assert!(pages[2][1].content.contains("<ol start=\"5\">"));
+    }
+
+    #[test]
+    fn single_element_pages_end() {
+        // No room for the heading: it would be split off again forever.
+        let elements = vec![element(Heading), element(Text), element(Image)];
+        let pages = apply(&limit(Check::MaxElements, 1, Action::Split), elements, "1").unwrap();
+        assert_eq!(natures(&pages), [[Heading], [Text], [Image]]);
+    }
+
+    #[test]
+    fn rules_in_turn() {
+        // Items split off before the elements are counted.
+        let rules = [
+            limit(Check::MaxListItems, 2, Action::Split),
+            limit(Check::MaxElements, 2, Action::Split),
+        ]
+        .concat();
+        let elements = vec![
+            written(TAG_HEADING, &["Items"]),
+            written(TAG_ULIST, &["a", "b", "c"]),
+            element(Image),
+        ];
+        let pages = apply(&rules, elements, "1").unwrap();
+        assert_eq!(
+            natures(&pages),
+            [[Heading, List], [Heading, Image], [Heading, List]]
+        );
+        assert_eq!(pages[2][1].source, ["c"]);
+    }
+}
+
+//    This file is part of StultusVisio.
+//
+//    StultusVisio is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    any later version.
+//
+//    StultusVisio is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with StultusVisio.  If not, see <https://www.gnu.org/licenses/>.
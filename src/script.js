@@ -1,14 +1,16 @@
 var slides = document.querySelectorAll('.slide');
 var currentslideIndex = 0;
-var n = 0;
 
 const popup = document.getElementById("popup");
 const popupText = document.getElementById("conteudo-popup");
 
 function popUpShow(){
+    // A deck of drafts only renders no slide.
+    var section = slides.length ? slides[currentslideIndex].dataset.section : '';
     popupText.innerHTML = `
                  <h1>Press <em>"t"</em><br> to quit this help</h1>
                  <h2>Slide ${currentslideIndex+1} of ${slides.length}</h2>
+                 ${section ? '<h2 class="section"></h2>' : ''}
                  <table>
                  <tr>
                    <th>Command</th><th>Key</th>
@@ -19,79 +21,181 @@ function popUpShow(){
                  <tr>
                    <td>Previous</td><td>k</td>
                  </tr>
+                 <tr>
+                   <td>5 slides on</td><td>5j</td>
+                 </tr>
                  <tr>
                    <td>First</td><td>gg</td>
+                 </tr>
                  <tr>
                    <td>Last</td><td>G</td>
                  </tr>
+                 <tr>
+                   <td>Slide 12</td><td>12G, :12 Enter</td>
+                 </tr>
+                 <tr>
+                   <td>Overview</td><td>o</td>
+                 </tr>
                  <tr>
                    <td>Print</td><td>p</td>
                  </tr>
                  </table>
                  <p> On mobile, swipe with landscape.</p>
                `;
+    // As text, the section name being the author's.
+    if (section) {popupText.querySelector('h2.section').textContent = section};
 }
 popUpShow();
 
+// Print mode: the slides one under the other, as printed, until "p"
+// is pressed again.
 function Printer() {
+  if (!document.documentElement.classList.toggle('printing')) {
+    showSlide();
+    return;
+  }
   for (var j = 0; j < slides.length; j++) {
-      slides[j].style.display= 'block';
+      slides[j].style.display= 'flex';
+      fitText(slides[j]);
   }
 };
 
-document.addEventListener('keydown', function(event) {
-  if (event.key === 'ArrowRight' || event.key === 'j') {
-    if (currentslideIndex < slides.length - 1){
-      var counter = parseInt(n);
-      if (counter === 0) {counter = counter + 1}
-      else if (counter > slides.length) {counter = 0};
-      currentslideIndex = currentslideIndex + counter;
-      n = 0;
-    }
-  }
-  else if (event.key === 'ArrowLeft' || event.key === 'k'){
-    if (currentslideIndex > 0) {
-     var counter = parseInt(n);
-     if (counter === 0) {counter = counter + 1};
-     currentslideIndex = currentslideIndex - counter;
-     if (currentslideIndex < 0 ){currentslideIndex = 0};
-     n = 0;
-    }
+// Keys are read as in vim: an optional count, then a command of one
+// key or more, like 5j, gg, 12G or :12<Enter>. A sequence left
+// unfinished for SEQUENCE_TIMEOUT ms is forgotten, but for the
+// :-command, which waits for Enter or Escape.
+const SEQUENCE_TIMEOUT = 1000;
+const MODIFIERS = ['Shift', 'Control', 'Alt', 'Meta', 'CapsLock'];
+var pending = '';
+var pendingTimer = null;
+
+function resetSequence() {
+  pending = '';
+  clearTimeout(pendingTimer);
+  pendingTimer = null;
+}
+
+function extendSequence(key) {
+  pending += key;
+  clearTimeout(pendingTimer);
+  pendingTimer = pending.startsWith(':') ? null : setTimeout(resetSequence, SEQUENCE_TIMEOUT);
+}
+
+function goToSlide(index) {
+  currentslideIndex = Math.min(Math.max(index, 0), slides.length - 1);
+}
+
+// The :-command, :12 going to the slide no. 12 on Enter.
+function commandKey(key) {
+  if (key === 'Enter') {
+    var number = parseInt(pending.slice(1), 10);
+    resetSequence();
+    if (number >= 1) {goToSlide(number - 1)};
+    return true;
   }
-  else if (event.key === 'p') {
-    Printer();
-    return;
+  if (key === 'Escape') {resetSequence()}
+  else if (key === 'Backspace') {pending = pending.slice(0, -1)}
+  else if (key >= '0' && key <= '9' && key.length === 1) {extendSequence(key)};
+  return false;
+}
+
+// Runs the command of `key` with the count typed before it, if any,
+// telling whether the slide shown may have changed.
+function runKey(key, count) {
+  var times = count || 1;
+  if (isMoving && ['ArrowUp', 'ArrowLeft', 'ArrowDown', 'ArrowRight'].includes(key)) {
+    for (var i = 0; i < times; i++) {moveCircle(key)};
+    return false;
   }
-  else if (event.key === 't') {
+  if (key === 'ArrowRight' || key === 'j') {goToSlide(currentslideIndex + times)}
+  else if (key === 'ArrowLeft' || key === 'k') {goToSlide(currentslideIndex - times)}
+  else if (key === 'G') {goToSlide(count ? count - 1 : slides.length - 1)}
+  else if (key === 'p') {Printer(); return false}
+  else if (key === 'o') {openOverview(); return false}
+  else if (key === 't') {
     if (popup.style.display === 'none') {popup.style.display = "block"}
     else {popup.style.display = "none"};
     popUpShow()
   }
-  else if (typeof event.key === 'string' && event.key >= '0' && event.key <= '9'){
-    if (n === 0) {n = `${event.key}`}else{n = `${n}${event.key}`}
-  }
+  else if (key === 'm') {toggleMovement()}
+  else if (key === 'x') {resizeMarker()}
+  else if (key === '-') {resizeFont()}
+  else {return false};
+  return true;
+}
 
-  else if (event.key === 'g'){
-    document.addEventListener('keydown', function(event) {
-       if (event.key === 'g') {currentslideIndex = 0};
-    })
+document.addEventListener('keydown', function(event) {
+  if (overview.classList.contains('open')) {
+    resetSequence();
+    overviewKey(event);
+    return;
   }
-  
-  else if (event.key === 'G') { currentslideIndex = slides.length - 1}
-
-  else if (event.key === 'm'){toggleMovement()}
+  var key = event.key;
+  if (event.ctrlKey || event.metaKey || event.altKey || MODIFIERS.includes(key)) {return};
+  if (document.documentElement.classList.contains('printing')) {
+    if (key === 'p') {Printer()};
+    return;
+  }
+  if (pending.startsWith(':')) {
+    if (commandKey(key)) {showSlide()};
+    event.preventDefault();
+    return;
+  }
+  var count = parseInt(pending, 10) || 0;
+  var prefix = pending.replace(/^[0-9]*/, '');
+  if (prefix === '' && key.length === 1 && key >= '0' && key <= '9') {
+    extendSequence(key);
+    return;
+  }
+  if (prefix === 'g') {
+    resetSequence();
+    if (key === 'g') {
+      goToSlide(count ? count - 1 : 0);
+      showSlide();
+    }
+    return;
+  }
+  if (key === 'g' || key === ':') {
+    if (key === ':') {resetSequence()};
+    extendSequence(key);
+    return;
+  }
+  resetSequence();
+  if (runKey(key, count)) {showSlide()};
+});
 
-  else if (isMoving && event.key === 'ArrowUp' 
-    || event.key === 'ArrowLeft' 
-    || event.key === 'ArrowDown' 
-    || event.key === 'ArrowRight') {moveCircle(event.key)}
+// The current slide lives in the URL, as #/14 or #/id, so it can be
+// reloaded, linked to and walked with back and forward.
+function slideHash(index) {
+  return '#/' + (slides[index].dataset.id || (index + 1));
+}
 
-  else if (event.key === 'x') {resizeMarker()}
-  
-  else if (event.key === '-') {resizeFont()}
+function slideFromHash() {
+  var target = decodeURIComponent(location.hash.replace(/^#\/?/, ''));
+  if (target === '') {return null};
+  for (var i = 0; i < slides.length; i++) {
+    if (slides[i].dataset.id === target) {return i};
+  }
+  var number = parseInt(target, 10);
+  if (String(number) === target && number >= 1 && number <= slides.length) {return number - 1};
+  return null;
+}
 
-  else {return};
+function followHash() {
+  var index = slideFromHash();
+  if (index !== null && index !== currentslideIndex) {
+    currentslideIndex = index;
+    showSlide();
+  }
+}
+window.addEventListener('hashchange', followHash);
+window.addEventListener('popstate', followHash);
 
+// Shows the current slide only, and lets the media of the slides
+// left and entered know about the move.
+var shownSlideIndex = -1;
+function showSlide() {
+  if (slides.length === 0) {return};
   for (var i = 0; i < slides.length; i++) {
     if (i === currentslideIndex) {
       slides[i].style.display = 'flex';
@@ -99,7 +203,182 @@ document.addEventListener('keydown', function(event) {
       slides[i].style.display = 'none';
     }
   }
+  if (shownSlideIndex !== currentslideIndex) {
+    var hash = slideHash(currentslideIndex);
+    if (location.hash !== hash) {
+      // The slide opened on load takes no step of the history.
+      if (shownSlideIndex < 0) {location.replace(hash)}
+      else {location.hash = hash};
+    }
+    if (shownSlideIndex >= 0) {leaveMedia(slides[shownSlideIndex])};
+    fitText(slides[currentslideIndex]);
+    enterMedia(slides[currentslideIndex]);
+    shownSlideIndex = currentslideIndex;
+  }
+}
+
+// Texts and tables shrink, down to --fit-min of their size, until
+// their part of the slide holds them. The slide must be shown.
+function fitText(slide) {
+  var root = getComputedStyle(document.documentElement);
+  var min = parseFloat(root.getPropertyValue('--fit-min')) || 1;
+  slide.querySelectorAll('.element').forEach(function(element) {
+    if (!element.querySelector('p, table')) {return};
+    var box = element.closest('.area') || slide;
+    var scale = 1;
+    element.style.fontSize = '';
+    while (scale > min && (box.scrollHeight > box.clientHeight
+      || box.scrollWidth > box.clientWidth)) {
+      scale = Math.max(min, scale - 0.05);
+      element.style.fontSize = (scale * 100) + '%';
+    }
+  });
+}
+
+// Clipped media rewinds to its start, and `autoplay` media starts,
+// as the slide is shown.
+function enterMedia(slide) {
+  slide.querySelectorAll('[data-start]').forEach(function(media) {
+    media.currentTime = parseFloat(media.dataset.start);
+  });
+  slide.querySelectorAll('[data-autoplay]').forEach(function(media) {
+    media.play().catch(function() {});
+  });
+}
+
+// Videos pause and `.audio stop` stops and rewinds as the slide is left.
+function leaveMedia(slide) {
+  slide.querySelectorAll('video').forEach(function(media) {
+    media.pause();
+  });
+  slide.querySelectorAll('[data-stop]').forEach(function(media) {
+    media.pause();
+    media.currentTime = 0;
+  });
+}
+
+// Clipped media keeps to its end, looping inside the clip if asked.
+document.querySelectorAll('[data-end]').forEach(function(media) {
+  media.addEventListener('timeupdate', function() {
+    if (media.currentTime >= parseFloat(media.dataset.end)) {
+      if (media.loop) {media.currentTime = parseFloat(media.dataset.start)}
+      else {media.pause()};
+    }
+  });
 });
+var linkedIndex = slideFromHash();
+if (linkedIndex !== null) {currentslideIndex = linkedIndex};
+showSlide();
+
+// The slides keep the size of their canvas, scaled as a whole to
+// fit the window.
+function fitCanvas() {
+  var root = getComputedStyle(document.documentElement);
+  var width = parseFloat(root.getPropertyValue('--slide-width'));
+  var height = parseFloat(root.getPropertyValue('--slide-height'));
+  var scale = Math.min(window.innerWidth / width, window.innerHeight / height);
+  document.documentElement.style.setProperty('--scale', scale);
+}
+window.addEventListener('resize', fitCanvas);
+fitCanvas();
+
+// Overview: thumbnails of the slides in a grid, under the heading of
+// their section, moved over with hjkl and opened with Enter.
+const overview = document.createElement('div');
+overview.id = 'overview';
+document.body.appendChild(overview);
+const THUMBNAIL_WIDTH = 240;
+var selectedIndex = 0;
+
+function openOverview() {
+  if (slides.length === 0) {return};
+  var root = getComputedStyle(document.documentElement);
+  var scale = THUMBNAIL_WIDTH / parseFloat(root.getPropertyValue('--slide-width'));
+  var height = parseFloat(root.getPropertyValue('--slide-height')) * scale;
+  overview.style.setProperty('--thumbnail-scale', scale);
+  overview.innerHTML = '';
+  var grid = null;
+  var section;
+  slides.forEach(function(slide, i) {
+    if (grid === null || slide.dataset.section !== section) {
+      section = slide.dataset.section;
+      if (section) {
+        var title = document.createElement('h2');
+        title.textContent = section;
+        overview.appendChild(title);
+      }
+      grid = document.createElement('div');
+      grid.className = 'overview-grid';
+      overview.appendChild(grid);
+    }
+    var thumbnail = document.createElement('div');
+    thumbnail.className = 'thumbnail';
+    thumbnail.style.width = THUMBNAIL_WIDTH + 'px';
+    thumbnail.style.height = height + 'px';
+    var copy = slide.cloneNode(true);
+    copy.style.display = 'flex';
+    copy.querySelectorAll('audio').forEach(function(media) {media.remove()});
+    thumbnail.appendChild(copy);
+    thumbnail.addEventListener('click', function() {closeOverview(i)});
+    grid.appendChild(thumbnail);
+  });
+  overview.classList.add('open');
+  thumbnails().forEach(function(thumbnail) {fitText(thumbnail.firstChild)});
+  selectThumbnail(currentslideIndex);
+}
+
+function closeOverview(index) {
+  overview.classList.remove('open');
+  overview.innerHTML = '';
+  currentslideIndex = index;
+  showSlide();
+}
+
+function thumbnails() {
+  return overview.querySelectorAll('.thumbnail');
+}
+
+function selectThumbnail(index) {
+  var all = thumbnails();
+  all[selectedIndex].classList.remove('selected');
+  selectedIndex = Math.min(Math.max(index, 0), all.length - 1);
+  all[selectedIndex].classList.add('selected');
+  all[selectedIndex].scrollIntoView({block: 'nearest'});
+}
+
+// The thumbnail of the next row up or down, the closest to the
+// selected one, since sections break the grid into several.
+function thumbnailBelow(step) {
+  var all = Array.from(thumbnails());
+  var from = all[selectedIndex].getBoundingClientRect();
+  var rows = all.filter(function(thumbnail) {
+    var top = thumbnail.getBoundingClientRect().top;
+    return step > 0 ? top > from.top : top < from.top;
+  });
+  if (rows.length === 0) {return selectedIndex};
+  var tops = rows.map(function(thumbnail) {return thumbnail.getBoundingClientRect().top});
+  var row = step > 0 ? Math.min.apply(null, tops) : Math.max.apply(null, tops);
+  var best = null;
+  rows.forEach(function(thumbnail) {
+    var box = thumbnail.getBoundingClientRect();
+    if (box.top !== row) {return};
+    if (best === null || Math.abs(box.left - from.left) < Math.abs(best.getBoundingClientRect().left - from.left)) {
+      best = thumbnail;
+    }
+  });
+  return all.indexOf(best);
+}
+
+function overviewKey(event) {
+  if (event.key === 'l' || event.key === 'ArrowRight') {selectThumbnail(selectedIndex + 1)}
+  else if (event.key === 'h' || event.key === 'ArrowLeft') {selectThumbnail(selectedIndex - 1)}
+  else if (event.key === 'j' || event.key === 'ArrowDown') {selectThumbnail(thumbnailBelow(1))}
+  else if (event.key === 'k' || event.key === 'ArrowUp') {selectThumbnail(thumbnailBelow(-1))}
+  else if (event.key === 'Enter') {closeOverview(selectedIndex)}
+  else if (event.key === 'o' || event.key === 'Escape') {closeOverview(currentslideIndex)}
+  else {return};
+  event.preventDefault();
+}
 
 const circle = document.getElementById('marcador');
 var sizeMarker = 1;